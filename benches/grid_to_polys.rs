@@ -0,0 +1,86 @@
+use bevy_game::tileset::grid::Grid;
+use bevy_game::tileset::load::{grid_to_polys, union_polys, TileGeometry};
+use bevy_game::tileset::tile::{RampOrientation, Surface, Tile};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use geo::{MultiPolygon, Scale, Translate};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const SIZE: usize = 512;
+
+/// A level-like grid: a border, platforms with gaps every few rows, pillars and scattered ramps
+fn synthetic_grid() -> (Grid<Tile>, Grid<Surface>) {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let mut rows = vec![vec![Tile::Air; SIZE]; SIZE];
+
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (x, tile) in row.iter_mut().enumerate() {
+            let border = x < 2 || y < 2 || x >= SIZE - 2 || y >= SIZE - 2;
+            let platform = y % 12 == 0 && (x / 8) % 5 != 0;
+            let pillar = x % 40 == 0 && y % 12 < 6;
+            if border || platform || pillar {
+                *tile = Tile::Solid;
+            } else if y % 12 == 11 && rng.gen_bool(0.05) {
+                *tile = Tile::Ramp(RampOrientation::SW);
+            }
+        }
+    }
+
+    let surfaces = Grid::new(
+        (0..SIZE)
+            .map(|y| {
+                (0..SIZE)
+                    .map(|x| match (x / 64 + y / 64) % 3 {
+                        1 => Surface::Ice,
+                        _ => Surface::Normal,
+                    })
+                    .collect()
+            })
+            .collect(),
+    );
+
+    (Grid::new(rows), surfaces)
+}
+
+/// The old approach: one polygon per tile, all unioned together
+fn per_tile_polys(grid: &Grid<Tile>, surfaces: &Grid<Surface>, geometry: TileGeometry) {
+    let mut layers: Vec<(Surface, Vec<MultiPolygon<f32>>)> = vec![];
+
+    for ((x, y), t) in grid.iter() {
+        let Some(mut p) = t.to_collider_verts() else {
+            continue;
+        };
+        let center = geometry.tile_center(x, y);
+        p.translate_mut(center.x, center.y);
+        p.scale_xy_mut(geometry.tile_size, geometry.tile_size);
+
+        let surface = *surfaces.get(x, y).unwrap();
+        let p = MultiPolygon::new(vec![p]);
+        match layers.iter_mut().find(|(s, _)| *s == surface) {
+            Some((_, polygons)) => polygons.push(p),
+            None => layers.push((surface, vec![p])),
+        }
+    }
+
+    for (_, polygons) in layers {
+        black_box(union_polys(polygons));
+    }
+}
+
+fn bench(c: &mut Criterion) {
+    let (grid, surfaces) = synthetic_grid();
+    let geometry = TileGeometry::default();
+
+    let mut group = c.benchmark_group("grid_to_polys 512x512");
+    group.sample_size(10);
+    group.bench_function("greedy rectangles", |b| {
+        b.iter(|| grid_to_polys(black_box(&grid), black_box(&surfaces), geometry))
+    });
+    group.bench_function("per tile", |b| {
+        b.iter(|| per_tile_polys(black_box(&grid), black_box(&surfaces), geometry))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Prints a reachability map of every level in `assets/levels`
+//! (or the directory given as the first argument), and whether its goal can be reached
+//! with the default player and web stats.
+//!
+//! See [`Reachability`] for what the map characters mean and what isn't modelled.
+
+use bevy_game::player::PlayerStats;
+use bevy_game::reachability::{Reach, Reachability};
+use bevy_game::tileset::load::{level_paths, load_level_file};
+use bevy_game::web::WebStats;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let dir = std::env::args().nth(1).unwrap_or_else(|| "assets/levels".to_string());
+    let paths = match level_paths(Path::new(&dir)) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("could not read {dir}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let player = PlayerStats::default();
+    let web = WebStats::default();
+
+    for path in paths {
+        let levels = match load_level_file(&path, true) {
+            Ok(levels) => levels,
+            Err(e) => {
+                eprintln!("{}: could not load: {e}", path.display());
+                continue;
+            }
+        };
+
+        for level in levels {
+            let name = level.label(&path);
+            let level = level.level;
+
+            let reach = Reachability::analyse(&level.grid, &level.surfaces, &player, &web, level.geometry.tile_size);
+            let verdict = match reach.goal {
+                None => "no goal or spawn",
+                Some(Reach::Unreached) => "goal unreachable",
+                Some(Reach::WebAir | Reach::WebGround) => "goal reachable with the web",
+                Some(Reach::Air | Reach::Ground) => "goal reachable without the web",
+            };
+
+            println!("{name}: {verdict}");
+            println!("{}", reach.to_text(&level.grid));
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,65 @@
+//! Precomputes the colliders of every PNG and text level in `assets/levels`
+//! (or the directory given as the first argument) into `.colliders` sidecar files.
+//!
+//! Run with `cargo run --bin bake-levels` after editing levels. Stale sidecars are
+//! ignored by the loaders, so forgetting to bake only costs load time.
+
+use bevy_game::tileset::bake::{baked_path, BakedColliders};
+use bevy_game::tileset::load::{level_paths, load_level_file};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let dir = std::env::args().nth(1).unwrap_or_else(|| "assets/levels".to_string());
+    let paths = match level_paths(Path::new(&dir)) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("could not read {dir}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = false;
+    for path in paths {
+        let baked = match load_level_file(&path, false) {
+            // only PNG and text levels are read with baked colliders
+            Ok(levels) => match levels.as_slice() {
+                [level] => level.hash.map(|hash| BakedColliders {
+                    hash,
+                    colliders: level.level.colliders.clone(),
+                }),
+                _ => None,
+            },
+            Err(e) => {
+                eprintln!("could not bake {}: {e}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+        let Some(baked) = baked else {
+            println!("skipped {}, its format doesn't use baked colliders", path.display());
+            continue;
+        };
+
+        match write(&path, baked) {
+            Ok(count) => println!("baked {} ({count} colliders)", path.display()),
+            Err(e) => {
+                eprintln!("could not bake {}: {e}", path.display());
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn write(path: &Path, baked: BakedColliders) -> Result<usize, Box<dyn Error>> {
+    fs::write(baked_path(path), baked.to_bytes())?;
+    Ok(baked.colliders.len())
+}
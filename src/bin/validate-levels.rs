@@ -0,0 +1,62 @@
+//! Checks every level in `assets/levels` (or the directory given as the first argument)
+//! for problems the game would only notice while playing, like a missing goal or player spawn.
+//!
+//! Levels are read with the same code as the asset loaders (see [`load_level_file`]),
+//! including palette sidecars, Tiled tilesets and collider generation.
+//! Exits with a failure if any level has an error, so it can run in CI. Warnings, like the missing goal
+//! of a test level, are only printed.
+
+use bevy_game::tileset::load::{level_paths, load_level_file};
+use bevy_game::tileset::validate::{validate, Severity};
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let dir = std::env::args().nth(1).unwrap_or_else(|| "assets/levels".to_string());
+    let paths = match level_paths(Path::new(&dir)) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("could not read {dir}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = false;
+    for path in paths {
+        let levels = match load_level_file(&path, true) {
+            Ok(levels) => levels,
+            Err(e) => {
+                failed = true;
+                eprintln!("{}: could not load: {e}", path.display());
+                continue;
+            }
+        };
+
+        for level in levels {
+            let name = level.label(&path);
+            let problems = validate(&level.level);
+
+            if problems.is_empty() {
+                println!("{name}: ok");
+            } else {
+                eprintln!("{name}:");
+                for problem in problems {
+                    let severity = match problem.severity() {
+                        Severity::Warning => "warning",
+                        Severity::Error => {
+                            failed = true;
+                            "error"
+                        }
+                    };
+                    eprintln!("    {severity}: {problem}");
+                }
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
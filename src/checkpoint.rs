@@ -0,0 +1,109 @@
+use crate::death::Dying;
+use crate::player::{CurrentLevel, Player};
+use crate::progress::{level_key, Progress};
+use crate::state::{InGame, Paused};
+use crate::tileset::load::{Checkpoint, TileGridAsset};
+use crate::tileset::tile::Tile;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+pub struct CheckpointPlugin;
+
+/// Activates checkpoints the player touches, which respawns then use instead of the spawn tile.
+/// The active checkpoint is saved in the level's [`Progress`], and restored when playing it again.
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(InGame), restore_checkpoint)
+            .add_systems(OnExit(InGame), clear_checkpoint)
+            .add_systems(
+                Update,
+                (
+                    touch_checkpoints.run_if(in_state(Paused(false))),
+                    color_checkpoints.run_if(
+                        resource_changed_or_removed::<ActiveCheckpoint>().or_else(checkpoints_spawned),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(InGame)),
+            );
+    }
+}
+
+/// The tile of the last checkpoint the player touched, only around while in game
+#[derive(Resource, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ActiveCheckpoint {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl ActiveCheckpoint {
+    /// Where to respawn in a tilemap anchored at `anchor`,
+    /// or `None` if the tile isn't a checkpoint (anymore)
+    pub fn position(&self, grid: &TileGridAsset, anchor: Vec3) -> Option<Vec3> {
+        (grid.grid.get(self.x, self.y) == Some(&Tile::Checkpoint))
+            .then(|| anchor + grid.geometry.tile_center(self.x, self.y).extend(0.0))
+    }
+}
+
+fn restore_checkpoint(
+    mut commands: Commands,
+    progress: Res<Progress>,
+    level: Option<Res<CurrentLevel>>,
+    asset_server: Res<AssetServer>,
+) {
+    let saved = level
+        .and_then(|level| level_key(&level, &asset_server))
+        .and_then(|key| progress.levels.get(&key)?.checkpoint);
+
+    if let Some((x, y)) = saved {
+        commands.insert_resource(ActiveCheckpoint { x, y });
+    }
+}
+
+fn clear_checkpoint(mut commands: Commands) {
+    commands.remove_resource::<ActiveCheckpoint>();
+}
+
+fn touch_checkpoints(
+    mut commands: Commands,
+    players: Query<&CollidingEntities, (With<Player>, Without<Dying>)>,
+    checkpoints: Query<&Checkpoint>,
+    active: Option<Res<ActiveCheckpoint>>,
+    mut progress: ResMut<Progress>,
+    level: Option<Res<CurrentLevel>>,
+    asset_server: Res<AssetServer>,
+) {
+    for colliding in players.iter() {
+        for checkpoint in checkpoints.iter_many(colliding.iter()) {
+            let touched = ActiveCheckpoint {
+                x: checkpoint.x,
+                y: checkpoint.y,
+            };
+            if active.as_deref() == Some(&touched) {
+                continue;
+            }
+
+            commands.insert_resource(touched);
+            if let Some(key) = level.as_deref().and_then(|level| level_key(level, &asset_server)) {
+                progress.level_mut(key).checkpoint = Some((touched.x, touched.y));
+                progress.save_or_warn();
+            }
+            return;
+        }
+    }
+}
+
+/// Checkpoint sprites spawn inactive, also when the level is spawned after restoring the active one
+fn checkpoints_spawned(checkpoints: Query<(), Added<Checkpoint>>) -> bool {
+    !checkpoints.is_empty()
+}
+
+fn color_checkpoints(active: Option<Res<ActiveCheckpoint>>, mut checkpoints: Query<(&Checkpoint, &mut Sprite)>) {
+    for (checkpoint, mut sprite) in checkpoints.iter_mut() {
+        let is_active = active
+            .as_deref()
+            .is_some_and(|active| (active.x, active.y) == (checkpoint.x, checkpoint.y));
+
+        sprite.color = Checkpoint::color(is_active);
+    }
+}
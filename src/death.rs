@@ -0,0 +1,167 @@
+use crate::checkpoint::ActiveCheckpoint;
+use crate::player::{Player, PlayerWeb};
+use crate::state::{GamePhase, Paused};
+use crate::tileset::edit::TileMap;
+use crate::tileset::load::Hazard;
+use crate::web::{WebSource, WebState};
+use avian2d::prelude::*;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+/// How long the player takes to die before respawning, in seconds
+const DEATH_TIME: f32 = 0.75;
+/// How far outside the level the player has to fall before dying, in tiles
+const KILL_MARGIN: f32 = 2.0;
+
+pub struct DeathPlugin;
+
+/// Kills the player when touching a hazard or leaving the level, sending a [`PlayerDied`],
+/// and respawns them at the [`ActiveCheckpoint`] or their [`RespawnPoint`] after a short fade out.
+/// R restarts from there without waiting to die.
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerDied>().add_systems(
+            Update,
+            (
+                (touch_hazards, fall_out_of_level, restart.run_if(input_just_pressed(KeyCode::KeyR))),
+                start_dying,
+                respawn,
+            )
+                .chain()
+                .run_if(in_state(Paused(false)).and_then(not(in_state(GamePhase::Completed)))),
+        );
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeathCause {
+    Hazard,
+    /// Fell out of the level's bounds
+    OutOfBounds,
+    /// Restarted from the last checkpoint on purpose
+    Restart,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerDied {
+    pub player: Entity,
+    pub cause: DeathCause,
+}
+
+/// Where the player comes back after dying without an [`ActiveCheckpoint`], in world space
+#[derive(Component, Debug, Copy, Clone)]
+pub struct RespawnPoint(pub Vec3);
+
+/// The player is dying and ignores input until the timer runs out
+#[derive(Component, Debug, Clone)]
+pub struct Dying(pub Timer);
+
+fn touch_hazards(
+    players: Query<(Entity, &CollidingEntities), (With<Player>, Without<Dying>)>,
+    hazards: Query<(), With<Hazard>>,
+    mut died: EventWriter<PlayerDied>,
+) {
+    for (player, colliding) in players.iter() {
+        if colliding.iter().any(|&entity| hazards.contains(entity)) {
+            died.send(PlayerDied {
+                player,
+                cause: DeathCause::Hazard,
+            });
+        }
+    }
+}
+
+/// The kill plane: a little below and to the sides of the tilemaps, leaving the top open for the web
+fn fall_out_of_level(
+    players: Query<(Entity, &GlobalTransform), (With<Player>, Without<Dying>)>,
+    maps: Query<(&TileMap, &GlobalTransform)>,
+    mut died: EventWriter<PlayerDied>,
+) {
+    if maps.is_empty() {
+        return;
+    }
+
+    for (player, transform) in players.iter() {
+        let pos = transform.translation().truncate();
+        let inside = maps.iter().any(|(map, map_transform)| {
+            let bounds = map.grid.bounds();
+            let anchor = map_transform.translation().truncate();
+            let margin = KILL_MARGIN * map.grid.geometry.tile_size;
+
+            pos.y > bounds.min.y + anchor.y - margin
+                && pos.x > bounds.min.x + anchor.x - margin
+                && pos.x < bounds.max.x + anchor.x + margin
+        });
+
+        if !inside {
+            died.send(PlayerDied {
+                player,
+                cause: DeathCause::OutOfBounds,
+            });
+        }
+    }
+}
+
+fn restart(players: Query<Entity, (With<Player>, Without<Dying>)>, mut died: EventWriter<PlayerDied>) {
+    for player in players.iter() {
+        died.send(PlayerDied {
+            player,
+            cause: DeathCause::Restart,
+        });
+    }
+}
+
+fn start_dying(
+    mut commands: Commands,
+    mut died: EventReader<PlayerDied>,
+    mut players: Query<(&mut LinearVelocity, &PlayerWeb, Has<Dying>)>,
+    mut webs: Query<(&mut WebSource, &mut WebState)>,
+) {
+    let mut handled = vec![];
+
+    for event in died.read() {
+        let Ok((mut velocity, web, dying)) = players.get_mut(event.player) else {
+            continue;
+        };
+        // a hazard and the kill plane in the same frame
+        if dying || handled.contains(&event.player) {
+            continue;
+        }
+        handled.push(event.player);
+
+        info!("player died: {:?}", event.cause);
+        velocity.0 = Vec2::ZERO;
+        if let Ok((mut source, mut state)) = webs.get_mut(web.0) {
+            source.release(&mut state, &mut commands);
+        }
+        commands
+            .entity(event.player)
+            .insert(Dying(Timer::from_seconds(DEATH_TIME, TimerMode::Once)));
+    }
+}
+
+fn respawn(
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut Dying, &mut Transform, &mut Sprite, &mut LinearVelocity, &RespawnPoint)>,
+    checkpoint: Option<Res<ActiveCheckpoint>>,
+    maps: Query<(&TileMap, &GlobalTransform)>,
+    time: Res<Time>,
+) {
+    for (player, mut dying, mut transform, mut sprite, mut velocity, respawn) in players.iter_mut() {
+        dying.0.tick(time.delta());
+        sprite.color.set_alpha(dying.0.fraction_remaining());
+
+        if dying.0.finished() {
+            transform.translation = checkpoint
+                .as_deref()
+                .and_then(|checkpoint| {
+                    maps.iter()
+                        .find_map(|(map, anchor)| checkpoint.position(&map.grid, anchor.translation()))
+                })
+                .unwrap_or(respawn.0);
+            velocity.0 = Vec2::ZERO;
+            sprite.color.set_alpha(1.0);
+            commands.entity(player).remove::<Dying>();
+        }
+    }
+}
@@ -0,0 +1,532 @@
+use crate::mouse::MouseCoords;
+use crate::player::CurrentLevel;
+use crate::state::{AppState, GameInfo, InGame};
+use crate::tileset::grid::Grid;
+use crate::tileset::load::{TileGeometry, TileGridAsset};
+use crate::tileset::palette::TilePalette;
+use crate::tileset::tile::{KeyColor, RampOrientation, Surface, Tile};
+use bevy::color::palettes::css::{DARK_CYAN, GOLD, GRAY, ORANGE, PURPLE, RED};
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use std::path::PathBuf;
+
+/// How many strokes can be undone
+const UNDO_LIMIT: usize = 100;
+
+/// Where levels that don't come from a PNG are saved, relative to the assets folder
+const NEW_LEVEL_PATH: &str = "levels/edited.png";
+
+/// The width and height in tiles of the empty level opened when the current one isn't loaded
+const NEW_LEVEL_SIZE: (usize, usize) = (40, 24);
+
+pub struct EditorPlugin;
+
+/// A level editor drawn with gizmos, entered from the menu.
+///
+/// Controls:
+/// - left mouse paints the selected brush, right mouse erases
+/// - 1-9 pick the brush: solid, ramp, erase, player spawn, goal, key, door, hazard, checkpoint
+/// - R turns the ramp brush, C changes the colour of the key and door brushes
+/// - Ctrl+Z / Ctrl+Y undo and redo
+/// - arrow keys pan the camera
+/// - F5 play-tests the level, and returns to the editor from the game
+/// - Ctrl+S saves the level as a PNG
+/// - Escape goes back to the menu
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Menu), close_editor)
+            .add_systems(OnEnter(AppState::Editor), (open_editor, spawn_editor_ui).chain())
+            .add_systems(
+                Update,
+                (
+                    select_brush,
+                    undo_redo,
+                    paint,
+                    pan_camera,
+                    update_brush_text,
+                    draw_level,
+                    play_test.run_if(input_just_pressed(KeyCode::F5)),
+                    save_level,
+                    back_to_menu.run_if(input_just_pressed(KeyCode::Escape)),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Editor).and_then(resource_exists::<EditorSession>)),
+            )
+            .add_systems(
+                Update,
+                back_to_editor.run_if(
+                    in_state(InGame)
+                        .and_then(resource_exists::<EditorSession>)
+                        .and_then(input_just_pressed(KeyCode::F5)),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Brush {
+    Solid,
+    Ramp(RampOrientation),
+    Erase,
+    Spawn,
+    Goal,
+    Key(KeyColor),
+    Door(KeyColor),
+    Hazard,
+    Checkpoint,
+}
+
+impl Brush {
+    fn tile(self) -> Tile {
+        match self {
+            Brush::Solid => Tile::Solid,
+            Brush::Ramp(orientation) => Tile::Ramp(orientation),
+            Brush::Erase => Tile::Air,
+            Brush::Spawn => Tile::Player,
+            Brush::Goal => Tile::Goal,
+            Brush::Key(color) => Tile::Key(color),
+            Brush::Door(color) => Tile::Door(color),
+            Brush::Hazard => Tile::Hazard,
+            Brush::Checkpoint => Tile::Checkpoint,
+        }
+    }
+
+    /// Placed once per level: painting it elsewhere moves it
+    fn unique(self) -> bool {
+        matches!(self, Brush::Spawn | Brush::Goal)
+    }
+}
+
+/// The level being edited. Outlives play-testing, and is dropped when going back to the menu.
+#[derive(Resource)]
+struct EditorSession {
+    level: Grid<(Tile, Surface)>,
+    geometry: TileGeometry,
+    /// Where the level is saved, relative to the assets folder
+    path: PathBuf,
+    brush: Brush,
+    undo: Vec<Grid<(Tile, Surface)>>,
+    redo: Vec<Grid<(Tile, Surface)>>,
+}
+
+impl EditorSession {
+    fn new(level: Grid<(Tile, Surface)>, geometry: TileGeometry, path: PathBuf) -> Self {
+        Self {
+            level,
+            geometry,
+            path,
+            brush: Brush::Solid,
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    /// Remembers the level before a stroke, dropping the oldest step past [`UNDO_LIMIT`]
+    fn checkpoint(&mut self) {
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(self.level.clone());
+        self.redo.clear();
+    }
+
+    fn paint(&mut self, x: usize, y: usize, brush: Brush) {
+        let tile = brush.tile();
+        if self.level.get(x, y).is_some_and(|&(t, _)| t == tile) {
+            return;
+        }
+
+        if brush.unique() {
+            for (px, py) in self.level.positions(|&(t, _)| t == tile) {
+                self.level.set(px, py, (Tile::Air, Surface::Normal));
+            }
+        }
+        self.level.set(x, y, (tile, Surface::Normal));
+    }
+}
+
+#[derive(Component)]
+struct BrushText;
+
+fn close_editor(mut commands: Commands) {
+    commands.remove_resource::<EditorSession>();
+}
+
+fn open_editor(
+    mut commands: Commands,
+    session: Option<Res<EditorSession>>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<TileGridAsset>>,
+    asset_server: Res<AssetServer>,
+) {
+    let bounds = match session {
+        // coming back from play-testing, keep editing where we left off
+        Some(session) => session.geometry.bounds(session.level.w, session.level.h),
+        None => {
+            let session = match levels.get(&current.0) {
+                Some(asset) => {
+                    let path = asset_server
+                        .get_path(&current.0)
+                        .map(|path| path.path().to_path_buf())
+                        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+                        .unwrap_or_else(|| PathBuf::from(NEW_LEVEL_PATH));
+                    EditorSession::new(asset.grid.clone().zip(asset.surfaces.clone()), asset.geometry, path)
+                }
+                None => {
+                    warn!("the current level isn't loaded, editing an empty one");
+                    let (w, h) = NEW_LEVEL_SIZE;
+                    EditorSession::new(
+                        Grid::filled(w, h, (Tile::Air, Surface::Normal)),
+                        TileGeometry::default(),
+                        PathBuf::from(NEW_LEVEL_PATH),
+                    )
+                }
+            };
+            let bounds = session.geometry.bounds(session.level.w, session.level.h);
+            commands.insert_resource(session);
+            bounds
+        }
+    };
+
+    commands.spawn((StateScoped(AppState::Editor), editor_camera(bounds)));
+}
+
+fn editor_camera(bounds: Rect) -> Camera2dBundle {
+    Camera2dBundle {
+        transform: Transform::from_translation(bounds.center().extend(10.0)),
+        projection: OrthographicProjection {
+            scaling_mode: ScalingMode::FixedVertical(bounds.height() + 32.0),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+fn spawn_editor_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(AppState::Editor),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn((
+                BrushText,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::linear_rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ),
+            ));
+            children.spawn(TextBundle::from_section(
+                "1-9 brush, R turn ramp, C key colour, right click erase, Ctrl+Z/Y undo/redo, F5 play-test, Ctrl+S save, Esc menu",
+                TextStyle {
+                    font_size: 15.0,
+                    color: Color::linear_rgb(0.6, 0.6, 0.6),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// The brushes picked by the number keys
+const BRUSH_KEYS: [(KeyCode, Brush); 9] = [
+    (KeyCode::Digit1, Brush::Solid),
+    (KeyCode::Digit2, Brush::Ramp(RampOrientation::SW)),
+    (KeyCode::Digit3, Brush::Erase),
+    (KeyCode::Digit4, Brush::Spawn),
+    (KeyCode::Digit5, Brush::Goal),
+    (KeyCode::Digit6, Brush::Key(KeyColor::Plain)),
+    (KeyCode::Digit7, Brush::Door(KeyColor::Plain)),
+    (KeyCode::Digit8, Brush::Hazard),
+    (KeyCode::Digit9, Brush::Checkpoint),
+];
+
+fn select_brush(input: Res<ButtonInput<KeyCode>>, mut session: ResMut<EditorSession>) {
+    let mut brush = session.brush;
+    if let Some(&(_, picked)) = BRUSH_KEYS.iter().find(|&&(key, _)| input.just_pressed(key)) {
+        brush = picked;
+    }
+
+    if input.just_pressed(KeyCode::KeyC) {
+        brush = match brush {
+            Brush::Key(color) => Brush::Key(next_color(color)),
+            Brush::Door(color) => Brush::Door(next_color(color)),
+            brush => brush,
+        };
+    }
+    if input.just_pressed(KeyCode::KeyR) {
+        brush = match brush {
+            // clockwise, starting from the bottom-left
+            Brush::Ramp(orientation) => Brush::Ramp(match orientation {
+                RampOrientation::SW => RampOrientation::NW,
+                RampOrientation::NW => RampOrientation::NE,
+                RampOrientation::NE => RampOrientation::SE,
+                RampOrientation::SE => RampOrientation::SW,
+            }),
+            brush => brush,
+        };
+    }
+
+    // only touch the session when something changed, it's redrawn on every change
+    if brush != session.brush {
+        session.brush = brush;
+    }
+}
+
+fn next_color(color: KeyColor) -> KeyColor {
+    match color {
+        KeyColor::Plain => KeyColor::Red,
+        KeyColor::Red => KeyColor::Green,
+        KeyColor::Green => KeyColor::Blue,
+        KeyColor::Blue => KeyColor::Plain,
+    }
+}
+
+fn ctrl(input: &ButtonInput<KeyCode>) -> bool {
+    input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+}
+
+fn undo_redo(input: Res<ButtonInput<KeyCode>>, mut session: ResMut<EditorSession>) {
+    if !ctrl(&input) {
+        return;
+    }
+
+    let session = &mut *session;
+    let (from, to) = if input.just_pressed(KeyCode::KeyZ) {
+        (&mut session.undo, &mut session.redo)
+    } else if input.just_pressed(KeyCode::KeyY) {
+        (&mut session.redo, &mut session.undo)
+    } else {
+        return;
+    };
+
+    if let Some(level) = from.pop() {
+        to.push(std::mem::replace(&mut session.level, level));
+    }
+}
+
+fn paint(
+    mouse: Res<ButtonInput<MouseButton>>,
+    coords: Res<MouseCoords>,
+    mut session: ResMut<EditorSession>,
+    mut last: Local<Option<(i64, i64)>>,
+) {
+    // a stroke that changed nothing isn't worth undoing.
+    // Checked first, since no button is pressed anymore on the frame the stroke ends
+    if mouse.any_just_released([MouseButton::Left, MouseButton::Right])
+        && !mouse.any_pressed([MouseButton::Left, MouseButton::Right])
+        && session.undo.last() == Some(&session.level)
+    {
+        session.undo.pop();
+    }
+
+    let brush = if mouse.pressed(MouseButton::Left) {
+        session.brush
+    } else if mouse.pressed(MouseButton::Right) {
+        Brush::Erase
+    } else {
+        *last = None;
+        return;
+    };
+
+    if mouse.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+        session.checkpoint();
+        *last = None;
+    }
+
+    // the cursor can move several tiles in a frame, paint the tiles in between too
+    let tile = session.geometry.tile_at(coords.0);
+    for (x, y) in line(last.unwrap_or(tile), tile) {
+        if session.level.get_i(x, y).is_some() {
+            session.paint(x as usize, y as usize, brush);
+        }
+    }
+    *last = Some(tile);
+}
+
+/// The tiles on the line from `from` to `to`, both included, each touching the previous one
+fn line(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (sx, sy) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let (mut x, mut y) = from;
+    let mut error = dx + dy;
+    let mut tiles = vec![from];
+
+    while (x, y) != to {
+        let twice = 2 * error;
+        if twice >= dy {
+            error += dy;
+            x += sx;
+        }
+        if twice <= dx {
+            error += dx;
+            y += sy;
+        }
+        tiles.push((x, y));
+    }
+
+    tiles
+}
+
+fn pan_camera(
+    input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), With<Camera>>,
+) {
+    let dir = Vec2::new(
+        input.pressed(KeyCode::ArrowRight) as i32 as f32 - input.pressed(KeyCode::ArrowLeft) as i32 as f32,
+        input.pressed(KeyCode::ArrowUp) as i32 as f32 - input.pressed(KeyCode::ArrowDown) as i32 as f32,
+    );
+
+    for (mut transform, projection) in camera.iter_mut() {
+        let speed = projection.area.height();
+        transform.translation += (dir * speed * time.delta_seconds()).extend(0.0);
+    }
+}
+
+fn update_brush_text(session: Res<EditorSession>, mut text: Query<&mut Text, With<BrushText>>) {
+    if !session.is_changed() {
+        return;
+    }
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format!("Brush: {:?}, saving to {}", session.brush, session.path.display());
+    }
+}
+
+fn draw_level(mut gizmos: Gizmos, session: Res<EditorSession>, coords: Res<MouseCoords>) {
+    let geometry = session.geometry;
+    let size = geometry.tile_size;
+    let bounds = geometry.bounds(session.level.w, session.level.h);
+    gizmos.rect_2d(bounds.center(), 0.0, bounds.size(), GRAY);
+
+    for ((x, y), &(tile, surface)) in session.level.iter() {
+        let center = geometry.tile_center(x, y);
+        let color = surface.color();
+
+        match tile {
+            Tile::Solid => gizmos.rect_2d(center, 0.0, Vec2::splat(size * 0.9), color),
+            Tile::Ramp(orientation) => {
+                let [a, b, c] = orientation.to_triangle().map(|p| center + p * size);
+                gizmos.linestrip_2d([a, b, c, a], color);
+            }
+            Tile::Player => {
+                gizmos.circle_2d(center, size * 0.4, RED);
+            }
+            Tile::Goal => gizmos.rect_2d(center, 0.0, Vec2::splat(size * 0.6), PURPLE),
+            Tile::Key(key) => {
+                gizmos.circle_2d(center, size * 0.2, key.color());
+            }
+            Tile::Door(door) => {
+                gizmos.rect_2d(center, 0.0, Vec2::new(size * 0.5, size * 0.9), door.color());
+            }
+            Tile::Hazard => {
+                let [a, b, c] = [Vec2::new(-0.4, -0.4), Vec2::new(0.0, 0.4), Vec2::new(0.4, -0.4)].map(|p| center + p * size);
+                gizmos.linestrip_2d([a, b, c, a], ORANGE);
+            }
+            Tile::Checkpoint => {
+                let (pole, flag) = (center + Vec2::new(-0.3, -0.4) * size, center + Vec2::new(-0.3, 0.4) * size);
+                gizmos.line_2d(pole, flag, DARK_CYAN);
+                gizmos.rect_2d(flag + Vec2::new(0.25, -0.15) * size, 0.0, Vec2::new(0.5, 0.3) * size, DARK_CYAN);
+            }
+            Tile::Air => {}
+        }
+    }
+
+    let (x, y) = geometry.tile_at(coords.0);
+    if session.level.get_i(x, y).is_some() {
+        gizmos.rect_2d(geometry.tile_center(x as usize, y as usize), 0.0, Vec2::splat(size), GOLD);
+    }
+}
+
+/// Builds the edited level into a new asset and plays it
+fn play_test(
+    mut commands: Commands,
+    session: Res<EditorSession>,
+    mut levels: ResMut<Assets<TileGridAsset>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let (grid, surfaces) = session.level.clone().unzip();
+    let asset = match TileGridAsset::new(grid, surfaces, session.geometry, default()) {
+        Ok(asset) => asset,
+        Err(e) => {
+            error!("can't play-test: {e}");
+            return;
+        }
+    };
+
+    commands.insert_resource(CurrentLevel(levels.add(asset)));
+    next_state.set(AppState::Game(GameInfo {
+        level: 0,
+        paused: false,
+    }));
+}
+
+fn back_to_editor(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::Editor);
+}
+
+fn back_to_menu(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::Menu);
+}
+
+/// Writes the level as a PNG the level loader reads, with the level's palette sidecar if it has one
+fn save_level(input: Res<ButtonInput<KeyCode>>, session: Res<EditorSession>) {
+    if !(ctrl(&input) && input.just_pressed(KeyCode::KeyS)) {
+        return;
+    }
+
+    let path = PathBuf::from("assets").join(&session.path);
+    let palette = match std::fs::read(path.with_extension("palette.ron")) {
+        Ok(bytes) => match ron::de::from_bytes(&bytes) {
+            Ok(palette) => palette,
+            Err(e) => {
+                error!("could not parse the palette of {}: {e}", path.display());
+                return;
+            }
+        },
+        Err(_) => TilePalette::default(),
+    };
+
+    let saved = palette
+        .encode_image(&session.level)
+        .map_err(|e| e.to_string())
+        .and_then(|image| image.save(&path).map_err(|e| e.to_string()));
+
+    match saved {
+        Ok(()) => info!("saved {}", path.display()),
+        Err(e) => error!("could not save {}: {e}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_have_no_gaps() {
+        assert_eq!(line((2, 3), (2, 3)), vec![(2, 3)]);
+        assert_eq!(line((0, 0), (3, 0)), vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(line((1, 1), (-1, -1)), vec![(1, 1), (0, 0), (-1, -1)]);
+
+        for to in [(7, 3), (-5, 9), (0, -6), (-8, -8)] {
+            let tiles = line((0, 0), to);
+            assert_eq!(tiles.last(), Some(&to));
+            for (a, b) in tiles.iter().zip(tiles.iter().skip(1)) {
+                assert!((a.0 - b.0).abs() <= 1 && (a.1 - b.1).abs() <= 1, "gap between {a:?} and {b:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Seeded random levels, strung together from hand-made room templates.
+//!
+//! Every room is [`ROOM_WIDTH`] by [`ROOM_HEIGHT`] tiles with its floor on the same row, so rooms fit
+//! side by side in any order and either way round. Rooms whose ceiling the web can't reach from the
+//! floor are left out, and levels the [`Reachability`] analysis can't finish are rolled again.
+
+use crate::player::PlayerStats;
+use crate::reachability::{Reach, Reachability};
+use crate::tileset::collider::{ColliderError, ColliderMode};
+use crate::tileset::grid::Grid;
+use crate::tileset::load::{TileGeometry, TileGridAsset};
+use crate::tileset::tile::{Surface, Tile};
+use crate::web::WebStats;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use thiserror::Error;
+
+pub const ROOM_WIDTH: usize = 12;
+pub const ROOM_HEIGHT: usize = 10;
+/// The row of the rooms' floor, which the player walks from one room into the next on
+const FLOOR: usize = 8;
+/// How many levels are rolled before giving up on a seed
+const MAX_ATTEMPTS: usize = 32;
+
+/// The first and last room, which get the spawn and goal
+const START: &str = "\
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+XX
+XX
+XX
+XX
+XX
+XX
+XXXXXXXXXXXX
+XXXXXXXXXXXX";
+
+const ROOMS: [&str; 7] = [
+    // flat
+    "\
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+
+
+
+
+
+
+XXXXXXXXXXXX
+XXXXXXXXXXXX",
+    // pit to swing over, falling in drops out of the level
+    "\
+XXXXXXXXXXXX
+
+
+
+
+
+
+
+XXX      XXX
+XXX      XXX",
+    // hill
+    "\
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+
+
+
+
+
+    /XX\\
+XXXXXXXXXXXX
+XXXXXXXXXXXX",
+    // step up and down
+    "\
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+
+
+
+
+    /XX\\
+   /XXXX\\
+XXXXXXXXXXXX
+XXXXXXXXXXXX",
+    // wide pit under a platform
+    "\
+XXXXXXXXXXXX
+
+
+
+    XXXX
+
+
+
+XX        XX
+XX        XX",
+    // icy floor under a ceiling the web slides off, with a grip on either side
+    "\
+XXXXXXXXXXXX
+XXXNNNNNNXXX
+
+
+
+
+
+
+XXIIIIIIIIXX
+XXXXXXXXXXXX",
+    // low tunnel
+    "\
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+XXXXXXXXXXXX
+XX,      `XX
+
+
+XXXXXXXXXXXX
+XXXXXXXXXXXX",
+];
+
+#[derive(Debug, Clone, Error)]
+pub enum GenerateError {
+    #[error("No playable level found in {0} attempts")]
+    Unplayable(usize),
+    #[error(transparent)]
+    Collider(#[from] ColliderError),
+}
+
+/// Builds random levels that the player with the given stats can finish
+#[derive(Clone)]
+pub struct LevelGenerator {
+    /// The number of rooms between the start and the goal room
+    pub rooms: usize,
+    pub player: PlayerStats,
+    pub web: WebStats,
+    pub geometry: TileGeometry,
+    pub collider_mode: ColliderMode,
+}
+
+impl Default for LevelGenerator {
+    fn default() -> Self {
+        Self {
+            rooms: 6,
+            player: PlayerStats::default(),
+            web: WebStats::default(),
+            geometry: TileGeometry::default(),
+            collider_mode: ColliderMode::default(),
+        }
+    }
+}
+
+impl LevelGenerator {
+    /// A level, the same one for the same seed and generator
+    pub fn generate(&self, seed: u64) -> Result<TileGridAsset, GenerateError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for _ in 0..MAX_ATTEMPTS {
+            let (grid, surfaces) = self.generate_grid(&mut rng).unzip();
+
+            let reach = Reachability::analyse(&grid, &surfaces, &self.player, &self.web, self.geometry.tile_size);
+            if reach.goal.is_some_and(Reach::reached) {
+                return Ok(TileGridAsset::new(grid, surfaces, self.geometry, self.collider_mode)?);
+            }
+        }
+
+        Err(GenerateError::Unplayable(MAX_ATTEMPTS))
+    }
+
+    /// A level without checking that it can be finished
+    pub fn generate_grid(&self, rng: &mut impl Rng) -> Grid<(Tile, Surface)> {
+        let templates = self.templates();
+
+        let start = template(START);
+        let mut rooms = vec![start.clone()];
+        rooms.extend((0..self.rooms).filter_map(|_| {
+            let room = templates.choose(rng)?;
+            Some(if rng.gen_bool(0.5) { flip_x(room) } else { room.clone() })
+        }));
+        rooms.push(flip_x(&start));
+
+        let w = rooms.len() * ROOM_WIDTH;
+        let mut level = Grid::filled(w, ROOM_HEIGHT, (Tile::Air, Surface::Normal));
+        for (i, room) in rooms.iter().enumerate() {
+            for ((x, y), &cell) in room.iter() {
+                level.set(i * ROOM_WIDTH + x, y, cell);
+            }
+        }
+
+        level.set(3, FLOOR - 1, (Tile::Player, Surface::Normal));
+        level.set(w - 4, FLOOR - 1, (Tile::Goal, Surface::Normal));
+
+        level
+    }
+
+    /// The rooms whose ceiling is within reach of the web everywhere on their floor
+    fn templates(&self) -> Vec<Grid<(Tile, Surface)>> {
+        let range = self.web.max_travel_distance / self.geometry.tile_size;
+
+        ROOMS
+            .iter()
+            .map(|room| template(room))
+            .filter(|room| ceiling_height(room) <= range)
+            .collect()
+    }
+}
+
+fn template(text: &str) -> Grid<(Tile, Surface)> {
+    let mut room = Grid::try_from(text).expect("room templates only use level characters");
+    assert_eq!(room.h, ROOM_HEIGHT, "room templates are {ROOM_HEIGHT} lines long");
+    room.resize(ROOM_WIDTH, ROOM_HEIGHT, (Tile::Air, Surface::Normal));
+    room
+}
+
+fn flip_x(room: &Grid<(Tile, Surface)>) -> Grid<(Tile, Surface)> {
+    let cells = room
+        .rows()
+        .flat_map(|row| row.iter().rev())
+        .map(|&(tile, surface)| match tile {
+            Tile::Ramp(orientation) => (Tile::Ramp(orientation.flip_x()), surface),
+            tile => (tile, surface),
+        })
+        .collect();
+    Grid::from_vec(room.w, room.h, cells)
+}
+
+/// The furthest any tile the player can stand on is from the nearest grappleable ceiling above it, in tiles.
+/// Counts as infinite when there's nothing to grapple.
+fn ceiling_height(room: &Grid<(Tile, Surface)>) -> f32 {
+    let solid = |x: usize, y: usize| matches!(room.get(x, y), Some((Tile::Solid | Tile::Ramp(_), _)));
+    let anchors = room
+        .positions(|(tile, surface)| *tile == Tile::Solid && surface.grappleable())
+        .into_iter()
+        .filter(|&(x, y)| !solid(x, y + 1))
+        .collect::<Vec<_>>();
+
+    room.positions(|_| true)
+        .into_iter()
+        .filter(|&(x, y)| y > 0 && !solid(x, y - 1) && solid(x, y))
+        .map(|(x, floor)| {
+            anchors
+                .iter()
+                .filter(|&&(_, y)| y + 1 < floor)
+                .map(|&(ax, ay)| (ax as f32 - x as f32).hypot(ay as f32 - (floor - 1) as f32))
+                .fold(f32::INFINITY, f32::min)
+        })
+        .fold(0.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_level() {
+        let generator = LevelGenerator::default();
+        for seed in 0..8 {
+            let grid = generator.generate_grid(&mut StdRng::seed_from_u64(seed));
+            assert_eq!(grid, generator.generate_grid(&mut StdRng::seed_from_u64(seed)));
+
+            let level = generator.generate(seed).unwrap();
+            assert_eq!(level.grid, generator.generate(seed).unwrap().grid);
+        }
+    }
+
+    #[test]
+    fn rooms_keep_their_height() {
+        let generator = LevelGenerator::default();
+        let grid = generator.generate_grid(&mut StdRng::seed_from_u64(0));
+        assert_eq!((grid.w, grid.h), ((generator.rooms + 2) * ROOM_WIDTH, ROOM_HEIGHT));
+    }
+
+    #[test]
+    fn generated_levels_can_be_finished() {
+        let generator = LevelGenerator::default();
+        for seed in 0..16 {
+            let level = generator.generate(seed).unwrap();
+            let reach = Reachability::analyse(
+                &level.grid,
+                &level.surfaces,
+                &generator.player,
+                &generator.web,
+                level.geometry.tile_size,
+            );
+            assert!(reach.goal.is_some_and(Reach::reached), "seed {seed} can't be finished");
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::player::Player;
+use crate::state::{AppState, GamePhase, InGame, Paused};
+use crate::tileset::load::Goal;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use std::time::Duration;
+
+pub struct GoalPlugin;
+
+/// Finishes the level when the player touches the goal,
+/// moving into `GamePhase::Completed` and sending a [`LevelCompleted`]
+impl Plugin for GoalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelCompleted>()
+            .init_resource::<LevelTimer>()
+            .add_systems(OnEnter(InGame), reset_timer)
+            .add_systems(OnEnter(GamePhase::Completed), show_completed)
+            .add_systems(
+                Update,
+                (
+                    tick_timer.run_if(in_state(Paused(false)).and_then(not(in_state(GamePhase::Completed)))),
+                    reach_goal.run_if(not(in_state(GamePhase::Completed))),
+                )
+                    .chain()
+                    .run_if(in_state(InGame)),
+            );
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LevelCompleted {
+    /// How long the level took, not counting time spent paused
+    pub time: Duration,
+    /// The `GameInfo::level` of the completed level
+    pub level: i32,
+}
+
+/// The time spent in the current level so far
+#[derive(Resource, Debug, Default)]
+pub struct LevelTimer(pub Stopwatch);
+
+fn reset_timer(mut timer: ResMut<LevelTimer>) {
+    timer.0.reset();
+}
+
+fn tick_timer(mut timer: ResMut<LevelTimer>, time: Res<Time>) {
+    timer.0.tick(time.delta());
+}
+
+fn reach_goal(
+    mut collisions: EventReader<CollisionStarted>,
+    mut completed: EventWriter<LevelCompleted>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    players: Query<(), With<Player>>,
+    goals: Query<(), With<Goal>>,
+    state: Res<State<AppState>>,
+    timer: Res<LevelTimer>,
+) {
+    let AppState::Game(info) = state.get() else {
+        return;
+    };
+
+    let touched = collisions.read().any(|&CollisionStarted(a, b)| {
+        (players.contains(a) && goals.contains(b)) || (players.contains(b) && goals.contains(a))
+    });
+
+    if touched {
+        next_phase.set(GamePhase::Completed);
+        completed.send(LevelCompleted {
+            time: timer.0.elapsed(),
+            level: info.level,
+        });
+    }
+}
+
+fn show_completed(mut commands: Commands, timer: Res<LevelTimer>) {
+    commands
+        .spawn((
+            Name::new("Level Complete"),
+            StateScoped(InGame),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("Level complete in {:.2}s", timer.0.elapsed_secs()),
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
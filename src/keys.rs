@@ -0,0 +1,90 @@
+use crate::player::Player;
+use crate::state::{InGame, Paused};
+use crate::tileset::edit::{TileMap, TileMapCommands};
+use crate::tileset::load::{Door, Key};
+use crate::tileset::tile::{KeyColor, Tile};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+pub struct KeysPlugin;
+
+/// Lets the player pick up keys and open doors with them.
+/// Both are removed from the player's [`TileMap`], which rebuilds the chunks they were in.
+impl Plugin for KeysPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (pick_up_keys, open_doors)
+                .chain()
+                .run_if(in_state(InGame).and_then(in_state(Paused(false)))),
+        );
+    }
+}
+
+/// The keys the player is carrying, in the order they were picked up
+#[derive(Component, Debug, Clone, Default)]
+pub struct HeldKeys(pub Vec<KeyColor>);
+
+impl HeldKeys {
+    /// Uses up a key that opens a door of colour `door`, preferring one of the same colour
+    /// so plain doors don't take the keys coloured doors need
+    pub fn take(&mut self, door: KeyColor) -> bool {
+        let index = self
+            .0
+            .iter()
+            .position(|&key| key == door)
+            .or_else(|| self.0.iter().position(|key| key.opens(door)));
+
+        index.map(|i| self.0.remove(i)).is_some()
+    }
+}
+
+fn pick_up_keys(
+    mut commands: Commands,
+    mut players: Query<(&CollidingEntities, &mut HeldKeys), With<Player>>,
+    keys: Query<&Key>,
+    maps: Query<&TileMap>,
+) {
+    for (colliding, mut held) in players.iter_mut() {
+        for key in keys.iter_many(colliding.iter()) {
+            // the key stays around until its chunk is rebuilt
+            let Ok(map) = maps.get(key.map) else { continue };
+            if map.grid.grid.get(key.x, key.y) != Some(&Tile::Key(key.color)) {
+                continue;
+            }
+
+            held.0.push(key.color);
+            commands.entity(key.map).set_tile(key.x, key.y, Tile::Air);
+        }
+    }
+}
+
+fn open_doors(
+    mut commands: Commands,
+    mut players: Query<(&CollidingEntities, &mut HeldKeys), With<Player>>,
+    doors: Query<&Door>,
+    maps: Query<&TileMap>,
+) {
+    for (colliding, mut held) in players.iter_mut() {
+        let mut opened = vec![];
+
+        for door in doors.iter_many(colliding.iter()) {
+            // like keys, doors stay around until their chunk is rebuilt,
+            // and the player may be touching several tiles of the same door
+            let Ok(map) = maps.get(door.map) else { continue };
+            if map.grid.grid.get(door.x, door.y) != Some(&Tile::Door(door.color)) || opened.contains(&(door.x, door.y)) {
+                continue;
+            }
+            if !held.take(door.color) {
+                continue;
+            }
+
+            let tiles = map.grid.grid.door_at(door.x, door.y);
+            let mut map_commands = commands.entity(door.map);
+            for &(x, y) in &tiles {
+                map_commands.set_tile(x, y, Tile::Air);
+            }
+            opened.extend(tiles);
+        }
+    }
+}
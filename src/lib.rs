@@ -12,25 +12,42 @@ use bevy_tnua_avian2d::TnuaAvian2dPlugin;
 use web::WebPlugin;
 
 use crate::camera::CameraPlugin;
+use crate::checkpoint::CheckpointPlugin;
+use crate::death::DeathPlugin;
+use crate::editor::EditorPlugin;
+use crate::goal::GoalPlugin;
+use crate::keys::KeysPlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
 use crate::mouse::MousePlugin;
 use crate::pause::PausePlugin;
 use crate::player::PlayerPlugin;
+use crate::progress::ProgressPlugin;
 use crate::state::{AppState, StatesPlugin};
 use crate::tileset::TilePlugin;
 
 mod audio;
 mod camera;
+pub mod checkpoint;
+pub mod death;
+mod editor;
+pub mod generate;
+pub mod goal;
+pub mod keys;
 mod loading;
 mod menu;
 mod pause;
-mod player;
+pub mod player;
+pub mod progress;
+pub mod reachability;
 mod state;
-mod tileset;
-mod web;
+pub mod tileset;
+pub mod web;
 mod mouse;
 
+/// The gravity of the game world, in pixels per second squared
+pub const GRAVITY: Vec2 = Vec2::new(0.0, -160.0);
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
@@ -49,9 +66,11 @@ impl Plugin for GamePlugin {
             CameraPlugin,
             WebPlugin,
             MousePlugin,
+            EditorPlugin,
+            (GoalPlugin, KeysPlugin, DeathPlugin, CheckpointPlugin, ProgressPlugin),
         ));
 
-        app.insert_resource::<Gravity>(Gravity(Vec2::Y * -160.0));
+        app.insert_resource::<Gravity>(Gravity(GRAVITY));
 
         #[cfg(debug_assertions)]
         {
@@ -1,5 +1,7 @@
 use crate::state::AppState;
+use crate::tileset::ldtk::LevelPack;
 use crate::tileset::load::TileGridAsset;
+use bevy::asset::UntypedAssetLoadFailedEvent;
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use bevy_asset_loader::prelude::*;
@@ -15,16 +17,76 @@ impl Plugin for LoadingPlugin {
         app.add_loading_state(
             LoadingState::new(AppState::Loading)
                 .continue_to_state(AppState::Menu)
+                .on_failure_continue_to_state(AppState::LoadError)
                 .load_collection::<AudioAssets>()
                 .load_collection::<TextureAssets>()
                 .load_collection::<Levels>(),
         )
+            .init_resource::<LoadErrors>()
+            .add_systems(Update, collect_load_errors.run_if(in_state(AppState::Loading)))
+            .add_systems(OnEnter(AppState::LoadError), show_load_errors)
             .register_type::<AudioAssets>()
             .register_type::<TextureAssets>()
             .register_type::<Levels>();
     }
 }
 
+/// The messages of every asset that failed to load
+#[derive(Resource, Default, Debug)]
+pub struct LoadErrors(pub Vec<String>);
+
+fn collect_load_errors(
+    mut failed: EventReader<UntypedAssetLoadFailedEvent>,
+    mut errors: ResMut<LoadErrors>,
+) {
+    for event in failed.read() {
+        error!("{}", event.error);
+        errors.0.push(event.error.to_string());
+    }
+}
+
+fn show_load_errors(mut commands: Commands, errors: Res<LoadErrors>) {
+    commands.spawn((StateScoped(AppState::LoadError), Camera2dBundle::default()));
+    commands
+        .spawn((
+            StateScoped(AppState::LoadError),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                "Could not load the game",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+
+            for error in errors.0.iter() {
+                children.spawn(TextBundle::from_section(
+                    error,
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::linear_rgb(0.9, 0.4, 0.4),
+                        ..default()
+                    },
+                ));
+            }
+        });
+}
+
 // the following asset collections will be loaded during the State `GameState::Loading`
 // when done loading, they will be inserted as resources (see <https://github.com/NiklasEi/bevy_asset_loader>)
 
@@ -44,6 +106,11 @@ pub struct TextureAssets {
     pub player: Handle<Image>,
     #[asset(path = "textures/block.png")]
     pub block: Handle<Image>,
+    /// `block` with an outline on every combination of open sides, ordered by neighbour mask
+    #[asset(path = "textures/block_autotile.png")]
+    pub block_autotile: Handle<Image>,
+    #[asset(texture_atlas_layout(tile_size_x = 16, tile_size_y = 16, columns = 4, rows = 4))]
+    pub block_autotile_layout: Handle<TextureAtlasLayout>,
     #[asset(path = "textures/ramp.png")]
     pub ramp: Handle<Image>,
 }
@@ -52,9 +119,7 @@ pub struct TextureAssets {
 pub struct Levels {
     #[asset(path = "levels/main-level.png")]
     pub main_level: Handle<TileGridAsset>,
-    // #[asset(
-    //     paths("levels/level-fall.png", "levels/level_fast.png", "levels/test_level.png", ),
-    //     collection(typed)
-    // )]
-    // pub level_map: Vec<Handle<TileGridAsset>>,
+    /// Every level of the LDtk world, each also loadable on its own as `levels/world.ldtk#<identifier>`
+    #[asset(path = "levels/world.ldtk")]
+    pub world: Handle<LevelPack>,
 }
@@ -1,5 +1,8 @@
+use crate::generate::LevelGenerator;
 use crate::loading::TextureAssets;
+use crate::player::CurrentLevel;
 use crate::state::GameInfo;
+use crate::tileset::load::TileGridAsset;
 use crate::AppState;
 use bevy::prelude::*;
 
@@ -78,6 +81,66 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
                         },
                     ));
                 });
+            let button_colors = ButtonColors::default();
+            children
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(140.0),
+                            height: Val::Px(50.0),
+                            margin: UiRect::top(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        background_color: button_colors.normal.into(),
+                        ..Default::default()
+                    },
+                    button_colors,
+                    ChangeState(AppState::Editor),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Edit",
+                        TextStyle {
+                            font_size: 40.0,
+                            color: Color::linear_rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    ));
+                });
+            let button_colors = ButtonColors::default();
+            children
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(140.0),
+                            height: Val::Px(50.0),
+                            margin: UiRect::top(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        background_color: button_colors.normal.into(),
+                        ..Default::default()
+                    },
+                    button_colors,
+                    ChangeState(AppState::Game(GameInfo {
+                        level: 0,
+                        paused: false,
+                    })),
+                    RandomLevel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Random",
+                        TextStyle {
+                            font_size: 40.0,
+                            color: Color::linear_rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    ));
+                });
         });
     commands
         .spawn((
@@ -181,8 +244,14 @@ struct ChangeState(AppState);
 #[derive(Component)]
 struct OpenLink(&'static str);
 
+/// Generates a new level to play before changing state
+#[derive(Component)]
+struct RandomLevel;
+
 fn click_play_button(
+    mut commands: Commands,
     mut next_state: ResMut<NextState<AppState>>,
+    mut levels: ResMut<Assets<TileGridAsset>>,
     mut interaction_query: Query<
         (
             &Interaction,
@@ -190,13 +259,28 @@ fn click_play_button(
             &ButtonColors,
             Option<&ChangeState>,
             Option<&OpenLink>,
+            Has<RandomLevel>,
         ),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color, button_colors, change_state, open_link) in &mut interaction_query {
+    for (interaction, mut color, button_colors, change_state, open_link, random_level) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
+                if random_level {
+                    let seed = rand::random();
+                    match LevelGenerator::default().generate(seed) {
+                        Ok(level) => {
+                            info!("generated level with seed {seed}");
+                            commands.insert_resource(CurrentLevel(levels.add(level)));
+                        }
+                        Err(e) => {
+                            error!("could not generate a level with seed {seed}: {e}");
+                            continue;
+                        }
+                    }
+                }
+
                 if let Some(state) = change_state {
                     next_state.set(state.0);
                 } else if let Some(link) = open_link {
@@ -1,8 +1,12 @@
 use crate::camera::{CameraRegion2d, CameraTarget};
+use crate::checkpoint::ActiveCheckpoint;
+use crate::death::{Dying, RespawnPoint};
+use crate::keys::HeldKeys;
 use crate::loading::{Levels, TextureAssets};
-use crate::state::{AppState, InGame, Paused};
+use crate::state::{AppState, GamePhase, InGame, Paused};
 use crate::tileset;
-use crate::tileset::load::{TileGridBundle, TileGridLoadEvent, TileGridSettings};
+use crate::tileset::autotile::{Autotile, AutotileRules};
+use crate::tileset::load::{TileGridAsset, TileGridBundle, TileGridLoadEvent, TileGridSettings};
 use crate::tileset::tile::Tile;
 use crate::web::{WebBundle, WebSource, WebState, WebStats};
 use avian2d::prelude::*;
@@ -18,7 +22,9 @@ pub struct PlayerPlugin;
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(InGame), spawn_level).add_systems(
+        app.add_systems(OnEnter(AppState::Menu), select_main_level)
+            .add_systems(OnEnter(InGame), spawn_level)
+            .add_systems(
             Update,
             (
                 spawn_player_and_camera
@@ -35,6 +41,9 @@ impl Plugin for PlayerPlugin {
 #[derive(Component)]
 pub struct Player;
 
+/// The radius of the player's collider, which has a density of 1
+pub const PLAYER_RADIUS: f32 = 6.0;
+
 #[derive(Component, Clone)]
 pub struct PlayerStats {
     pub speed: f32,
@@ -42,10 +51,34 @@ pub struct PlayerStats {
     pub walk: TnuaBuiltinWalk,
 }
 
+impl Default for PlayerStats {
+    fn default() -> Self {
+        Self {
+            speed: 128.0,
+            air_multiplier: 0.5,
+            walk: TnuaBuiltinWalk {
+                float_height: 6.0,
+                acceleration: 256.0,
+                air_acceleration: 0.0,
+                cling_distance: 0.5,
+                ..default()
+            },
+        }
+    }
+}
+
 #[derive(Component, Copy, Clone, Debug, Reflect)]
 pub struct PlayerWeb(pub Entity);
 
-fn spawn_level(mut commands: Commands, assets: Res<TextureAssets>, levels: Res<Levels>) {
+/// The level spawned when entering the game, and the one opened by the editor
+#[derive(Resource, Clone, Debug)]
+pub struct CurrentLevel(pub Handle<TileGridAsset>);
+
+fn select_main_level(mut commands: Commands, levels: Res<Levels>) {
+    commands.insert_resource(CurrentLevel(levels.main_level.clone()));
+}
+
+fn spawn_level(mut commands: Commands, assets: Res<TextureAssets>, level: Res<CurrentLevel>) {
     
     // dbg!(&levels.level_map);
     commands.spawn((
@@ -55,9 +88,14 @@ fn spawn_level(mut commands: Commands, assets: Res<TextureAssets>, levels: Res<L
             settings: TileGridSettings {
                 solid_texture: assets.block.clone(),
                 ramp_texture: assets.ramp.clone(),
-                tile_size: 16.0,
+                autotile: Some(Autotile {
+                    texture: assets.block_autotile.clone(),
+                    layout: assets.block_autotile_layout.clone(),
+                    rules: AutotileRules::sixteen(),
+                }),
+                ..default()
             },
-            tile_grid: levels.main_level.clone(),
+            tile_grid: level.0.clone(),
             transform: Transform::from_xyz(0.0, 0.0, -10.0),
             ..default()
         },
@@ -69,8 +107,11 @@ fn spawn_player_and_camera(
     mut tile_grid: EventReader<TileGridLoadEvent>,
     texture_assets: Res<TextureAssets>,
     global_pos: Query<&GlobalTransform>,
+    mut existing: Query<(&mut Transform, &mut LinearVelocity, &mut RespawnPoint), With<Player>>,
+    mut regions: Query<&mut CameraRegion2d>,
+    checkpoint: Option<Res<ActiveCheckpoint>>,
 ) {
-    for TileGridLoadEvent(grid, settings, parent) in tile_grid.read() {
+    for TileGridLoadEvent(grid, _, parent) in tile_grid.read() {
         let grid_anchor = global_pos.get(*parent).unwrap().translation();
 
         let Some((p_x, p_y)) = grid.grid
@@ -84,11 +125,36 @@ fn spawn_player_and_camera(
             continue;
         };
 
-        let mut pos = grid_anchor;
-        pos.x += p_x as f32 * settings.tile_size;
-        pos.y -= p_y as f32 * settings.tile_size;
+        let pos = grid_anchor + grid.geometry.tile_center(p_x, p_y).extend(0.0);
+
+        let mut rect = grid.bounds();
+        rect.min += grid_anchor.xy();
+        rect.max += grid_anchor.xy();
+
+        // The level was reloaded, keep the player where they are unless they're now stuck in a wall
+        if let Ok((mut transform, mut velocity, mut respawn)) = existing.get_single_mut() {
+            // the spawn tile may have moved
+            respawn.0 = pos;
 
-        let radius = 6.0;
+            let (x, y) = grid.geometry.tile_at(transform.translation.xy() - grid_anchor.xy());
+            if matches!(grid.grid.get_i(x, y), Some(Tile::Solid | Tile::Ramp(_) | Tile::Door(_))) {
+                transform.translation = pos;
+                velocity.0 = Vec2::ZERO;
+            }
+
+            for mut region in regions.iter_mut() {
+                region.0 = rect;
+            }
+            continue;
+        }
+
+        // pick up from a checkpoint saved the last time this level was played
+        let start = checkpoint
+            .as_deref()
+            .and_then(|checkpoint| checkpoint.position(grid, grid_anchor))
+            .unwrap_or(pos);
+
+        let radius = PLAYER_RADIUS;
         let collider = Collider::circle(radius);
         let scale = 0.9;
         let sensor = Collider::circle(radius * scale);
@@ -100,19 +166,9 @@ fn spawn_player_and_camera(
                 Name::new("Player"),
                 StateScoped(InGame),
                 Player,
-                PlayerStats {
-                    speed: 128.0,
-                    air_multiplier: 0.5,
-                    walk: TnuaBuiltinWalk {
-                        float_height: 6.0,
-                        acceleration: 256.0,
-                        air_acceleration: 0.0,
-                        cling_distance: 0.5,
-                        ..default()
-                    },
-                },
+                PlayerStats::default(),
                 SpriteBundle {
-                    transform: Transform::from_translation(pos),
+                    transform: Transform::from_translation(start),
                     texture: texture_assets.player.clone(),
                     ..default()
                 },
@@ -123,24 +179,12 @@ fn spawn_player_and_camera(
                 RigidBody::Dynamic,
                 LockedAxes::ROTATION_LOCKED,
                 Restitution::new(0.0).with_combine_rule(CoefficientCombine::Min),
+                (CollidingEntities::default(), HeldKeys::default(), RespawnPoint(pos)),
                 // ExternalForce::new(Vec2::ZERO).with_persistence(false),
             ))
             .id();
 
-        // let bounds = CameraRegion2d(Rec)
-        let mut bottom_right = grid_anchor.xy();
-        bottom_right.x += grid.grid.w as f32 * settings.tile_size;
-        bottom_right.y -= grid.grid.h as f32 * settings.tile_size;
-
-        let mut center = grid_anchor;
-
-        center.x += grid.grid.w as f32 * settings.tile_size / 2.0;
-        center.y -= grid.grid.h as f32 * settings.tile_size / 2.0;
-        center.z += 10.0;
-        
-        let mut rect = Rect::from_corners(grid_anchor.xy(), bottom_right);
-        rect.min += Vec2::new(-1.0, 1.0) * settings.tile_size / 2.0;
-        rect.max += Vec2::new(-1.0, 1.0) * settings.tile_size / 2.0;
+        let center = rect.center().extend(grid_anchor.z + 10.0);
 
         commands.spawn((
             Name::new("Camera"),
@@ -173,12 +217,7 @@ fn spawn_player_and_camera(
             WebBundle {
                 web_source: WebSource { player, joint: None},
                 web_state: WebState::default(),
-                web_stats: WebStats {
-                    pull_force: 96000.0,
-                    travel_speed: 640.0,
-                    radius: 2.0,
-                    max_travel_distance: 240.0,
-                },
+                web_stats: WebStats::default(),
             },
             SpatialBundle::default(),
         )).id();
@@ -189,15 +228,20 @@ fn spawn_player_and_camera(
 }
 
 pub fn move_player(
-    mut player: Query<(&mut TnuaController, &mut LinearVelocity, &PlayerStats, &PlayerWeb, &GlobalTransform)>,
+    mut player: Query<(&mut TnuaController, &mut LinearVelocity, &PlayerStats, &PlayerWeb, &GlobalTransform, Has<Dying>)>,
     web: Query<&WebState>,
     input: Res<ButtonInput<KeyCode>>,
     q_pos: Query<&GlobalTransform>,
     time: Res<Time>,
+    phase: Res<State<GamePhase>>,
 ) {
-    for (mut controller, mut vel, stats, p_web, transform) in player.iter_mut() {
-        let x =
-            input.pressed(KeyCode::KeyD) as i32 as f32 - input.pressed(KeyCode::KeyA) as i32 as f32;
+    for (mut controller, mut vel, stats, p_web, transform, dying) in player.iter_mut() {
+        // keep feeding the controller after the level is done or while dying, so the player stops instead of sliding on
+        let x = if dying || *phase.get() == GamePhase::Completed {
+            0.0
+        } else {
+            input.pressed(KeyCode::KeyD) as i32 as f32 - input.pressed(KeyCode::KeyA) as i32 as f32
+        };
 
         controller.basis(TnuaBuiltinWalk {
             desired_forward: Vec3::X * x,
@@ -210,7 +254,8 @@ pub fn move_player(
             match web_state {
                 WebState::Attached { target, offset, .. } => {
                     let p_1 = transform.translation().truncate();
-                    let p_2 = q_pos.get(*target).unwrap().translation().truncate();
+                    let Ok(p_2) = q_pos.get(*target) else { continue };
+                    let p_2 = p_2.translation().truncate();
                     
                     let mut dir = (p_2 - p_1).normalize_or_zero();
                     // rotate 90deg clockwise to get tangent
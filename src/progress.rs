@@ -0,0 +1,152 @@
+use crate::goal::LevelCompleted;
+use crate::player::CurrentLevel;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+pub struct ProgressPlugin;
+
+/// Remembers the last checkpoint of every level.
+/// Loaded on startup and saved whenever it changes, to a file on desktop and to local storage on the web.
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Progress>()
+            .add_systems(Startup, load_progress)
+            .add_systems(Update, clear_completed_checkpoints.run_if(on_event::<LevelCompleted>()));
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProgressError {
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("Could not read or write the progress file: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(target_arch = "wasm32")]
+    #[error("Could not access local storage: {0}")]
+    Storage(String),
+    #[error("Could not parse the progress file: {0}")]
+    Parse(#[from] ron::de::SpannedError),
+    #[error("Could not serialize progress: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// The progress of every level played, by asset path
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Progress {
+    pub levels: BTreeMap<String, LevelProgress>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LevelProgress {
+    /// The tile of the last checkpoint reached since the level was last completed
+    pub checkpoint: Option<(usize, usize)>,
+}
+
+impl Progress {
+    /// Reads the saved progress, which is empty if nothing was saved yet
+    pub fn load() -> Result<Self, ProgressError> {
+        match storage::read()? {
+            Some(text) => Ok(ron::from_str(&text)?),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), ProgressError> {
+        let text = ron::ser::to_string_pretty(self, default())?;
+        storage::write(&text)
+    }
+
+    /// Saves, logging rather than returning failures since progress is nice to have
+    pub fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!("{e}");
+        }
+    }
+
+    pub fn level_mut(&mut self, key: String) -> &mut LevelProgress {
+        self.levels.entry(key).or_default()
+    }
+}
+
+/// The key of a level in [`Progress`], or `None` for levels that aren't files,
+/// like generated levels or ones being play-tested in the editor
+pub fn level_key(level: &CurrentLevel, asset_server: &AssetServer) -> Option<String> {
+    asset_server.get_path(level.0.id()).map(|path| path.to_string())
+}
+
+fn load_progress(mut commands: Commands) {
+    match Progress::load() {
+        Ok(progress) => commands.insert_resource(progress),
+        Err(e) => warn!("starting without saved progress: {e}"),
+    }
+}
+
+fn clear_completed_checkpoints(
+    mut completed: EventReader<LevelCompleted>,
+    mut progress: ResMut<Progress>,
+    level: Option<Res<CurrentLevel>>,
+    asset_server: Res<AssetServer>,
+) {
+    completed.clear();
+    let Some(key) = level.and_then(|level| level_key(&level, &asset_server)) else {
+        return;
+    };
+
+    // the next attempt starts from the spawn again
+    progress.level_mut(key).checkpoint = None;
+    progress.save_or_warn();
+}
+
+/// Saves progress to a file next to the game
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use super::ProgressError;
+    use std::fs;
+    use std::io::ErrorKind;
+
+    /// Where progress is saved, relative to the working directory
+    const PROGRESS_PATH: &str = "progress.ron";
+
+    pub fn read() -> Result<Option<String>, ProgressError> {
+        match fs::read_to_string(PROGRESS_PATH) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn write(text: &str) -> Result<(), ProgressError> {
+        Ok(fs::write(PROGRESS_PATH, text)?)
+    }
+}
+
+/// Saves progress to the browser's local storage, since there is no file system on the web
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use super::ProgressError;
+    use web_sys::Storage;
+
+    /// The local storage key progress is saved under
+    const PROGRESS_KEY: &str = "progress";
+
+    fn local_storage() -> Result<Storage, ProgressError> {
+        web_sys::window()
+            .ok_or_else(|| ProgressError::Storage("no window".to_string()))?
+            .local_storage()
+            .map_err(|e| ProgressError::Storage(format!("{e:?}")))?
+            .ok_or_else(|| ProgressError::Storage("local storage is disabled".to_string()))
+    }
+
+    pub fn read() -> Result<Option<String>, ProgressError> {
+        local_storage()?
+            .get_item(PROGRESS_KEY)
+            .map_err(|e| ProgressError::Storage(format!("{e:?}")))
+    }
+
+    pub fn write(text: &str) -> Result<(), ProgressError> {
+        local_storage()?
+            .set_item(PROGRESS_KEY, text)
+            .map_err(|e| ProgressError::Storage(format!("{e:?}")))
+    }
+}
@@ -0,0 +1,404 @@
+//! A rough, offline estimate of where the player can get to in a level.
+//!
+//! The player is modelled as a point the size of a tile: it walks along the ground, climbs ramps,
+//! falls with the speed it walked off a ledge with, and from the ground can shoot the web in any
+//! direction. An attached web lets it be pulled towards the anchor, or swing around it no higher
+//! than it started. Jumping off a swing, chaining webs in mid-air and opening doors are not modelled,
+//! so a level can be possible even if the analysis says otherwise.
+
+use crate::player::{PlayerStats, PLAYER_RADIUS};
+use crate::tileset::grid::Grid;
+use crate::tileset::tile::{Surface, Tile};
+use crate::web::WebStats;
+use crate::GRAVITY;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+
+/// How many directions the web is shot in from each tile
+const WEB_DIRECTIONS: usize = 64;
+/// The time step of falls, in seconds
+const FALL_STEP: f32 = 1.0 / 60.0;
+/// Falls taking longer than this, in seconds, are given up on
+const MAX_FALL_TIME: f32 = 10.0;
+
+/// How the player can get to a tile
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Reach {
+    #[default]
+    Unreached,
+    /// Passed through in the air with the web
+    WebAir,
+    /// Stood on, but only with the web
+    WebGround,
+    /// Passed through in the air without the web
+    Air,
+    /// Stood on by walking and falling from the spawn
+    Ground,
+}
+
+impl Reach {
+    pub fn reached(self) -> bool {
+        self != Reach::Unreached
+    }
+
+    /// The character of an empty tile in [`Reachability::to_text`]
+    fn char(self) -> char {
+        match self {
+            Reach::Unreached => ' ',
+            Reach::WebAir => ':',
+            Reach::WebGround => 'W',
+            Reach::Air => '.',
+            Reach::Ground => 'w',
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reachability {
+    pub map: Grid<Reach>,
+    /// How the goal is reached, `None` if the level has no goal or spawn
+    pub goal: Option<Reach>,
+}
+
+impl Reachability {
+    /// Estimates where the player can get to from the first spawn of the level
+    pub fn analyse(grid: &Grid<Tile>, surfaces: &Grid<Surface>, player: &PlayerStats, web: &WebStats, tile_size: f32) -> Self {
+        let analyser = Analyser::new(grid, surfaces, player, web, tile_size);
+        let Some(&spawn) = grid.positions(|tile| *tile == Tile::Player).first() else {
+            return Self {
+                map: Grid::filled(grid.w, grid.h, Reach::Unreached),
+                goal: None,
+            };
+        };
+
+        let walk = analyser.explore(spawn, false);
+        let with_web = analyser.explore(spawn, true);
+
+        let cells = (0..grid.h)
+            .flat_map(|y| (0..grid.w).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                walk.get(x, y)
+                    .copied()
+                    .flatten()
+                    .map(|ground| if ground { Reach::Ground } else { Reach::Air })
+                    .or_else(|| {
+                        with_web
+                            .get(x, y)
+                            .copied()
+                            .flatten()
+                            .map(|ground| if ground { Reach::WebGround } else { Reach::WebAir })
+                    })
+                    .unwrap_or_default()
+            })
+            .collect();
+        let map = Grid::from_vec(grid.w, grid.h, cells);
+
+        let goal = grid
+            .positions(|tile| *tile == Tile::Goal)
+            .into_iter()
+            .filter_map(|(x, y)| map.get(x, y).copied())
+            .max();
+
+        Self { map, goal }
+    }
+
+    /// The level in the plain-text format, with the empty tiles replaced by how they are reached:
+    /// `w` ground and `.` air reached by walking, `W` ground and `:` air only reached with the web
+    pub fn to_text(&self, grid: &Grid<Tile>) -> String {
+        grid.rows()
+            .zip(self.map.rows())
+            .map(|(tiles, reach)| {
+                tiles
+                    .iter()
+                    .zip(reach)
+                    .map(|(&tile, &reach)| match tile {
+                        Tile::Air => reach.char(),
+                        tile => char::from(tile),
+                    })
+                    .chain(std::iter::once('\n'))
+                    .collect::<String>()
+            })
+            .collect()
+    }
+}
+
+/// The level and player, in tiles and seconds, with y pointing down like the grid
+struct Analyser<'a> {
+    grid: &'a Grid<Tile>,
+    surfaces: &'a Grid<Surface>,
+    gravity: f32,
+    walk_speed: f32,
+    web_range: f32,
+    /// The acceleration of the player while being pulled by the web
+    pull: f32,
+}
+
+impl<'a> Analyser<'a> {
+    fn new(grid: &'a Grid<Tile>, surfaces: &'a Grid<Surface>, player: &PlayerStats, web: &WebStats, tile_size: f32) -> Self {
+        let mass = std::f32::consts::PI * PLAYER_RADIUS * PLAYER_RADIUS;
+
+        Self {
+            grid,
+            surfaces,
+            gravity: -GRAVITY.y / tile_size,
+            walk_speed: player.speed / tile_size,
+            web_range: web.max_travel_distance / tile_size,
+            pull: web.pull_force / mass / tile_size,
+        }
+    }
+
+    fn solid(&self, x: i64, y: i64) -> bool {
+        matches!(self.grid.get_i(x, y), Some(Tile::Solid | Tile::Ramp(_) | Tile::Door(_)))
+    }
+
+    fn free(&self, x: i64, y: i64) -> bool {
+        self.grid.get_i(x, y).is_some() && !self.solid(x, y)
+    }
+
+    fn hazard(&self, x: i64, y: i64) -> bool {
+        self.grid.get_i(x, y) == Some(&Tile::Hazard)
+    }
+
+    fn ground(&self, x: i64, y: i64) -> bool {
+        self.free(x, y) && !self.hazard(x, y) && self.solid(x, y + 1)
+    }
+
+    /// Every tile reachable from `spawn`: `Some(true)` for ground, `Some(false)` for air
+    fn explore(&self, spawn: (usize, usize), use_web: bool) -> Grid<Option<bool>> {
+        let mut seen = Grid::filled(self.grid.w, self.grid.h, None);
+        let mut queue = VecDeque::new();
+
+        let visit = |seen: &mut Grid<Option<bool>>, queue: &mut VecDeque<(i64, i64)>, (path, landing): Fall| {
+            for (x, y) in path {
+                if let Some(cell @ None) = seen.get_mut(x as usize, y as usize) {
+                    *cell = Some(false);
+                }
+            }
+            if let Some((x, y)) = landing {
+                if let Some(cell) = seen.get_mut(x as usize, y as usize).filter(|cell| **cell != Some(true)) {
+                    *cell = Some(true);
+                    queue.push_back((x, y));
+                }
+            }
+        };
+
+        visit(&mut seen, &mut queue, self.fall(Vec2::new(spawn.0 as f32, spawn.1 as f32), Vec2::ZERO));
+
+        while let Some((x, y)) = queue.pop_front() {
+            for dir in [-1, 1] {
+                let nx = x + dir;
+                if self.free(nx, y) {
+                    // walking off a ledge at any speed up to full, or onto more ground
+                    for speed in [0.0, 0.25, 0.5, 0.75, 1.0] {
+                        let velocity = Vec2::new(dir as f32 * speed * self.walk_speed, 0.0);
+                        visit(&mut seen, &mut queue, self.fall(Vec2::new(nx as f32, y as f32), velocity));
+                    }
+                } else if matches!(self.grid.get_i(nx, y), Some(Tile::Ramp(_))) && self.free(nx, y - 1) {
+                    visit(&mut seen, &mut queue, (vec![], Some((nx, y - 1))));
+                }
+            }
+
+            if use_web {
+                for anchor in self.anchors(x, y) {
+                    for fall in self.web_moves(x, y, anchor) {
+                        visit(&mut seen, &mut queue, fall);
+                    }
+                }
+            }
+        }
+
+        seen
+    }
+
+    /// The points the web attaches to when shot from tile (x, y)
+    fn anchors(&self, x: i64, y: i64) -> Vec<Vec2> {
+        let start = Vec2::new(x as f32, y as f32);
+        let step = 0.25;
+
+        (0..WEB_DIRECTIONS)
+            .filter_map(|i| {
+                let dir = Vec2::from_angle(i as f32 / WEB_DIRECTIONS as f32 * TAU);
+                (1..=(self.web_range / step) as usize).find_map(|n| {
+                    let pos = start + dir * (n as f32 * step);
+                    let (tx, ty) = (pos.x.round() as i64, pos.y.round() as i64);
+                    if !self.solid(tx, ty) {
+                        return None;
+                    }
+                    let grappleable = self.surfaces.get_i(tx, ty).copied().unwrap_or_default().grappleable();
+                    Some(grappleable.then_some(pos))
+                })?
+            })
+            .collect()
+    }
+
+    /// Where the player can go from (x, y) with the web attached at `anchor`
+    fn web_moves(&self, x: i64, y: i64, anchor: Vec2) -> Vec<Fall> {
+        let start = Vec2::new(x as f32, y as f32);
+        let radius = start.distance(anchor);
+        let mut falls = vec![];
+
+        // pulled towards the anchor, letting go anywhere on the way
+        let dir = (anchor - start).normalize_or_zero();
+        let mut last = None;
+        for n in 1..radius as usize {
+            let pos = start + dir * n as f32;
+            if !self.free(pos.x.round() as i64, pos.y.round() as i64) {
+                break;
+            }
+            falls.push(self.fall(pos, Vec2::ZERO));
+            last = Some((pos, n as f32));
+        }
+        // or keeping the speed of the full pull
+        if let Some((pos, distance)) = last {
+            falls.push(self.fall(pos, dir * (2.0 * self.pull * distance).sqrt()));
+        }
+
+        // swinging below the anchor, no higher than where we started
+        let mut seen = vec![(x, y)];
+        let mut queue = VecDeque::from([(x, y)]);
+        while let Some((sx, sy)) = queue.pop_front() {
+            falls.push(self.fall(Vec2::new(sx as f32, sy as f32), Vec2::ZERO));
+
+            for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                let (nx, ny) = (sx + dx, sy + dy);
+                let inside = Vec2::new(nx as f32, ny as f32).distance(anchor) <= radius;
+                if ny >= y && inside && self.free(nx, ny) && !seen.contains(&(nx, ny)) {
+                    seen.push((nx, ny));
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+
+        falls
+    }
+
+    /// Follows a fall from `pos` with `velocity`, sliding along walls and ceilings,
+    /// until it lands on the ground, leaves the level or hits a hazard
+    fn fall(&self, mut pos: Vec2, mut velocity: Vec2) -> Fall {
+        let tile = |pos: Vec2| (pos.x.round() as i64, pos.y.round() as i64);
+        let mut path = vec![tile(pos)];
+
+        for _ in 0..(MAX_FALL_TIME / FALL_STEP) as usize {
+            let (x, y) = tile(pos);
+            if self.hazard(x, y) {
+                return (path, None);
+            }
+            if velocity.y >= 0.0 && self.ground(x, y) {
+                return (path, Some((x, y)));
+            }
+
+            velocity.y += self.gravity * FALL_STEP;
+            let mut next = pos + velocity * FALL_STEP;
+            let (nx, ny) = tile(next);
+
+            if self.grid.get_i(nx, ny).is_none() && !self.solid(nx, ny) && (nx, ny) != (x, y) {
+                // fell out of the level
+                return (path, None);
+            }
+            if self.solid(nx, y) {
+                velocity.x = 0.0;
+                next.x = pos.x;
+            }
+            if self.solid(tile(next).0, ny) {
+                velocity.y = 0.0;
+                next.y = pos.y;
+            }
+
+            pos = next;
+            if path.last() != Some(&tile(pos)) {
+                path.push(tile(pos));
+            }
+        }
+
+        (path, None)
+    }
+}
+
+/// The tiles passed through by a fall and where it lands, if it does
+type Fall = (Vec<(i64, i64)>, Option<(i64, i64)>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::load::TileGeometry;
+
+    fn analyse(text: &str) -> Reachability {
+        let (grid, surfaces) = Grid::<(Tile, Surface)>::try_from(text).unwrap().unzip();
+        let tile_size = TileGeometry::default().tile_size;
+        Reachability::analyse(&grid, &surfaces, &PlayerStats::default(), &WebStats::default(), tile_size)
+    }
+
+    #[test]
+    fn walks_to_the_goal() {
+        let reach = analyse(
+            "\
+XXXXXXXXXX
+X        X
+X@      0X
+XXXXXXXXXX",
+        );
+        assert_eq!(reach.goal, Some(Reach::Ground));
+    }
+
+    #[test]
+    fn falls_to_the_goal() {
+        let reach = analyse(
+            "\
+XXXXXXXXXX
+X@       X
+XXXX     X
+X        X
+X       0X
+XXXXXXXXXX",
+        );
+        assert_eq!(reach.goal, Some(Reach::Ground));
+    }
+
+    #[test]
+    fn swings_over_a_pit_to_the_goal() {
+        let reach = analyse(
+            "\
+XXXXXXXXXXXXXXXXXXXXXX
+X                    X
+X                    X
+X                    X
+X@                  0X
+XXXX              XXXX",
+        );
+        assert_eq!(reach.goal, Some(Reach::WebGround));
+    }
+
+    #[test]
+    fn the_web_slides_off_no_grapple_ceilings() {
+        let reach = analyse(
+            "\
+NNNNNNNNNNNNNNNNNNNNNN
+N                    N
+N                    N
+N                    N
+N@                  0N
+NNNN              NNNN",
+        );
+        assert_eq!(reach.goal, Some(Reach::Unreached));
+    }
+
+    #[test]
+    fn walls_block_the_goal() {
+        let reach = analyse(
+            "\
+XXXXXXXXXX
+X   X    X
+X@  X   0X
+XXXXXXXXXX",
+        );
+        assert_eq!(reach.goal, Some(Reach::Unreached));
+        assert_eq!(reach.map.get(2, 2), Some(&Reach::Ground));
+        assert_eq!(reach.map.get(6, 2), Some(&Reach::Unreached));
+    }
+
+    #[test]
+    fn no_spawn_means_no_goal() {
+        assert_eq!(analyse("X0X\nXXX").goal, None);
+    }
+}
@@ -19,7 +19,11 @@ impl Plugin for StatesPlugin {
 pub enum AppState {
     #[default]
     Loading,
+    /// An asset failed to load, see [`crate::loading::LoadErrors`]
+    LoadError,
     Menu,
+    /// The level editor, see [`crate::editor::EditorPlugin`]
+    Editor,
     Game(GameInfo),
 }
 
@@ -63,6 +67,7 @@ impl ComputedStates for Paused {
     }
 }
 
+/// Moved into `Completed` when the player reaches the goal, see [`crate::goal::GoalPlugin`]
 #[derive(SubStates, Clone, Eq, PartialEq, Hash, Debug, Default)]
 #[allow(dead_code)]
 #[source(InGame = InGame)]
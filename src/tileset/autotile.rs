@@ -0,0 +1,242 @@
+use crate::tileset::grid::Grid;
+use crate::tileset::tile::{RampOrientation, Tile};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const NORTH: u8 = 1;
+pub const EAST: u8 = 2;
+pub const SOUTH: u8 = 4;
+pub const WEST: u8 = 8;
+pub const NORTH_EAST: u8 = 16;
+pub const SOUTH_EAST: u8 = 32;
+pub const SOUTH_WEST: u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+/// The neighbour bit, grid offset and, for edges, the ramps whose full side faces back at us
+const EDGES: [(u8, (i64, i64), [RampOrientation; 2]); 4] = [
+    (NORTH, (0, -1), [RampOrientation::SW, RampOrientation::SE]),
+    (EAST, (1, 0), [RampOrientation::SW, RampOrientation::NW]),
+    (SOUTH, (0, 1), [RampOrientation::NW, RampOrientation::NE]),
+    (WEST, (-1, 0), [RampOrientation::SE, RampOrientation::NE]),
+];
+
+/// The neighbour bit, grid offset, the two edges it sits between
+/// and the one ramp whose triangle doesn't touch our corner
+const CORNERS: [(u8, (i64, i64), u8, RampOrientation); 4] = [
+    (NORTH_EAST, (1, -1), NORTH | EAST, RampOrientation::NE),
+    (SOUTH_EAST, (1, 1), SOUTH | EAST, RampOrientation::SE),
+    (SOUTH_WEST, (-1, 1), SOUTH | WEST, RampOrientation::SW),
+    (NORTH_WEST, (-1, -1), NORTH | WEST, RampOrientation::NW),
+];
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum NeighbourMode {
+    /// Only the edge neighbours count, giving up to 16 different sprites
+    #[default]
+    Four,
+    /// Corners count too, but only when both edges next to them are connected, giving up to 47 sprites
+    Eight,
+}
+
+/// Picks the atlas sprite of a solid tile from its neighbours
+#[derive(Debug, Clone, Default, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct AutotileRules {
+    pub mode: NeighbourMode,
+    /// Checked in order, the first matching rule wins
+    pub rules: Vec<AutotileRule>,
+    /// The sprite used when no rule matches
+    pub fallback: usize,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct AutotileRule {
+    /// The exact set of connected neighbours, see [`NORTH`], [`EAST`] etc.
+    pub mask: u8,
+    /// Neighbours that must be connected through a ramp, to draw ramp joints
+    #[serde(default)]
+    pub ramps: u8,
+    pub index: usize,
+}
+
+/// A tileset atlas for the solid tiles and the rules to pick its sprites
+#[derive(Debug, Clone, Default)]
+pub struct Autotile {
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub rules: AutotileRules,
+}
+
+impl AutotileRules {
+    /// Rules for a 4x4 atlas with one sprite per 4-neighbour mask, sprite `i` being mask `i`
+    pub fn sixteen() -> Self {
+        Self {
+            mode: NeighbourMode::Four,
+            rules: (0..16)
+                .map(|mask| AutotileRule {
+                    mask,
+                    ramps: 0,
+                    index: mask as usize,
+                })
+                .collect(),
+            fallback: 15,
+        }
+    }
+
+    /// The connected neighbours of the tile at (x, y) and which of them are ramps.
+    /// Solid tiles and ramps with a full side (or corner) facing the tile are connected,
+    /// as is everything outside the grid.
+    pub fn neighbours(&self, grid: &Grid<Tile>, x: usize, y: usize) -> (u8, u8) {
+        let (x, y) = (x as i64, y as i64);
+        let (mut mask, mut ramps) = (0, 0);
+
+        for (bit, (dx, dy), facing) in EDGES {
+            match grid.get_i(x + dx, y + dy) {
+                None | Some(Tile::Solid) => mask |= bit,
+                Some(Tile::Ramp(orientation)) if facing.contains(orientation) => {
+                    mask |= bit;
+                    ramps |= bit;
+                }
+                _ => {}
+            }
+        }
+
+        if self.mode == NeighbourMode::Eight {
+            for (bit, (dx, dy), edges, away) in CORNERS {
+                if mask & edges != edges {
+                    continue;
+                }
+
+                match grid.get_i(x + dx, y + dy) {
+                    None | Some(Tile::Solid) => mask |= bit,
+                    Some(Tile::Ramp(orientation)) if *orientation != away => {
+                        mask |= bit;
+                        ramps |= bit;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        (mask, ramps)
+    }
+
+    /// The atlas index for the tile at (x, y)
+    pub fn index(&self, grid: &Grid<Tile>, x: usize, y: usize) -> usize {
+        let (mask, ramps) = self.neighbours(grid, x, y);
+
+        self.rules
+            .iter()
+            .find(|rule| rule.mask == mask && rule.ramps & ramps == rule.ramps)
+            .map_or(self.fallback, |rule| rule.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RampOrientation::*;
+
+    const EDGE_BITS: u8 = NORTH | EAST | SOUTH | WEST;
+
+    /// A solid tile in the middle of a 3x3 grid, with `tile` at each of the `neighbours` offsets
+    fn surrounded(neighbours: &[(i64, i64)], tile: Tile) -> Grid<Tile> {
+        let mut grid = Grid::filled(3, 3, Tile::Air);
+        grid.set(1, 1, Tile::Solid);
+        for &(dx, dy) in neighbours {
+            grid.set((1 + dx) as usize, (1 + dy) as usize, tile);
+        }
+        grid
+    }
+
+    fn rules(mode: NeighbourMode) -> AutotileRules {
+        AutotileRules {
+            mode,
+            ..AutotileRules::sixteen()
+        }
+    }
+
+    #[test]
+    fn ramps_connect_with_their_full_sides() {
+        let connecting = [
+            (NORTH, (0, -1), [SW, SE]),
+            (EAST, (1, 0), [SW, NW]),
+            (SOUTH, (0, 1), [NW, NE]),
+            (WEST, (-1, 0), [SE, NE]),
+        ];
+
+        for (bit, offset, ramps) in connecting {
+            for orientation in [SW, SE, NE, NW] {
+                let grid = surrounded(&[offset], Tile::Ramp(orientation));
+                let expected = if ramps.contains(&orientation) { (bit, bit) } else { (0, 0) };
+                let neighbours = rules(NeighbourMode::Four).neighbours(&grid, 1, 1);
+                assert_eq!(neighbours, expected, "{orientation:?} at {offset:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn ramps_connect_with_their_corners() {
+        let away = [
+            (NORTH_EAST, (1, -1), NE),
+            (SOUTH_EAST, (1, 1), SE),
+            (SOUTH_WEST, (-1, 1), SW),
+            (NORTH_WEST, (-1, -1), NW),
+        ];
+
+        for (bit, offset, away) in away {
+            for orientation in [SW, SE, NE, NW] {
+                let mut grid = surrounded(&[(0, -1), (1, 0), (0, 1), (-1, 0)], Tile::Solid);
+                grid.set((1 + offset.0) as usize, (1 + offset.1) as usize, Tile::Ramp(orientation));
+                let expected = if orientation == away { (EDGE_BITS, 0) } else { (EDGE_BITS | bit, bit) };
+                let neighbours = rules(NeighbourMode::Eight).neighbours(&grid, 1, 1);
+                assert_eq!(neighbours, expected, "{orientation:?} at {offset:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn corners_need_both_edges() {
+        let grid = surrounded(&[(0, -1), (1, -1)], Tile::Solid);
+        assert_eq!(rules(NeighbourMode::Eight).neighbours(&grid, 1, 1), (NORTH, 0));
+    }
+
+    #[test]
+    fn outside_the_grid_is_connected() {
+        let grid = Grid::filled(1, 1, Tile::Solid);
+        assert_eq!(rules(NeighbourMode::Eight).neighbours(&grid, 0, 0), (u8::MAX, 0));
+    }
+
+    #[test]
+    fn four_neighbour_sprite_is_the_mask() {
+        let grid = surrounded(&[(0, -1), (-1, 0)], Tile::Solid);
+        assert_eq!(AutotileRules::sixteen().index(&grid, 1, 1), (NORTH | WEST) as usize);
+    }
+
+    #[test]
+    fn eight_neighbour_sprites_match_corners_and_ramps() {
+        let full = EDGE_BITS | NORTH_EAST;
+        let rules = AutotileRules {
+            mode: NeighbourMode::Eight,
+            rules: vec![
+                AutotileRule {
+                    mask: full,
+                    ramps: NORTH_EAST,
+                    index: 5,
+                },
+                AutotileRule {
+                    mask: full,
+                    ramps: 0,
+                    index: 4,
+                },
+            ],
+            fallback: 0,
+        };
+
+        let mut grid = surrounded(&[(0, -1), (1, 0), (0, 1), (-1, 0)], Tile::Solid);
+        assert_eq!(rules.index(&grid, 1, 1), 0);
+        grid.set(2, 0, Tile::Solid);
+        assert_eq!(rules.index(&grid, 1, 1), 4);
+        grid.set(2, 0, Tile::Ramp(SW));
+        assert_eq!(rules.index(&grid, 1, 1), 5);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::tileset::collider::{ColliderShape, TileCollider, COLLIDER_CHUNK_SIZE, COLLIDER_GENERATOR_VERSION};
+use crate::tileset::tile::Surface;
+use bevy::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Pre-computed colliders of a level, stored next to it as `<level>.colliders`
+/// by the `bake-levels` binary.
+///
+/// The loaders only use them when `hash` matches the level they are loading (see [`level_hash`]),
+/// so a stale sidecar falls back to generating the colliders at load time.
+#[derive(Debug, Clone)]
+pub struct BakedColliders {
+    pub hash: u64,
+    pub colliders: Vec<TileCollider>,
+}
+
+#[derive(Debug, Error)]
+pub enum BakeError {
+    #[error("Not a baked collider file")]
+    Magic,
+    #[error("Baked in layout version {0}, but version {} is needed", MAGIC[3])]
+    Version(u8),
+    #[error("Baked from a different level, settings or generator version")]
+    Stale,
+    #[error("Unexpected end of file")]
+    Truncated,
+    #[error("Unknown surface {0}")]
+    Surface(u8),
+    #[error("Unknown collider shape {0}")]
+    Shape(u8),
+}
+
+/// Identifies the file and its layout, bump the last byte when the layout changes
+const MAGIC: &[u8; 4] = b"TGC\x03";
+
+/// The sidecar file for the level at `path`
+pub fn baked_path(path: &Path) -> PathBuf {
+    path.with_extension("colliders")
+}
+
+/// FNV-1a hash of everything the colliders of a level depend on:
+/// its bytes, its palette file (if any), the loader settings, the collider chunk size
+/// and the version of the code generating them (the crate version and [`COLLIDER_GENERATOR_VERSION`])
+pub fn level_hash(level: &[u8], palette: Option<&[u8]>, settings: &impl Serialize) -> u64 {
+    let settings = ron::to_string(settings).expect("loader settings are always serializable");
+    let chunk_size = (COLLIDER_CHUNK_SIZE as u64).to_le_bytes();
+    let generator = COLLIDER_GENERATOR_VERSION.to_le_bytes();
+
+    [
+        MAGIC.as_slice(),
+        env!("CARGO_PKG_VERSION").as_bytes(),
+        &generator,
+        level,
+        palette.unwrap_or_default(),
+        settings.as_bytes(),
+        &chunk_size,
+    ]
+    .into_iter()
+    .flat_map(|part| (part.len() as u64).to_le_bytes().into_iter().chain(part.iter().copied()))
+    .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl BakedColliders {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer(MAGIC.to_vec());
+        out.u64(self.hash);
+        out.u32(self.colliders.len() as u32);
+
+        for collider in self.colliders.iter() {
+            out.u8(collider.surface as u8);
+            out.vec2(collider.offset);
+            out.u32(collider.chunk.0 as u32);
+            out.u32(collider.chunk.1 as u32);
+
+            match &collider.shape {
+                ColliderShape::Triangle(tri) => {
+                    out.u8(0);
+                    out.triangle(tri);
+                }
+                ColliderShape::Compound(tris) => {
+                    out.u8(1);
+                    out.u32(tris.len() as u32);
+                    tris.iter().for_each(|tri| out.triangle(tri));
+                }
+                ColliderShape::Polyline(loops) => {
+                    out.u8(2);
+                    out.u32(loops.len() as u32);
+                    for ring in loops {
+                        out.u32(ring.len() as u32);
+                        ring.iter().for_each(|&p| out.vec2(p));
+                    }
+                }
+                ColliderShape::ConvexDecomposition(parts) => {
+                    out.u8(3);
+                    out.u32(parts.len() as u32);
+                    for part in parts {
+                        out.u32(part.len() as u32);
+                        part.iter().for_each(|&p| out.vec2(p));
+                    }
+                }
+            }
+        }
+
+        out.0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BakeError> {
+        let mut input = Reader(bytes);
+        match input.take(4)? {
+            magic if magic == MAGIC => {}
+            [b'T', b'G', b'C', version] => return Err(BakeError::Version(*version)),
+            _ => return Err(BakeError::Magic),
+        }
+
+        let hash = input.u64()?;
+        let colliders = (0..input.u32()?)
+            .map(|_| {
+                let surface = match input.u8()? {
+                    0 => Surface::Normal,
+                    1 => Surface::Ice,
+                    2 => Surface::Sticky,
+                    3 => Surface::Bouncy,
+                    4 => Surface::NoGrapple,
+                    s => return Err(BakeError::Surface(s)),
+                };
+                let offset = input.vec2()?;
+                let chunk = (input.u32()? as usize, input.u32()? as usize);
+
+                let shape = match input.u8()? {
+                    0 => ColliderShape::Triangle(input.triangle()?),
+                    1 => ColliderShape::Compound(
+                        (0..input.u32()?).map(|_| input.triangle()).collect::<Result<_, _>>()?,
+                    ),
+                    2 => ColliderShape::Polyline(
+                        (0..input.u32()?)
+                            .map(|_| (0..input.u32()?).map(|_| input.vec2()).collect())
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    3 => ColliderShape::ConvexDecomposition(
+                        (0..input.u32()?)
+                            .map(|_| (0..input.u32()?).map(|_| input.vec2()).collect())
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    s => return Err(BakeError::Shape(s)),
+                };
+
+                Ok(TileCollider {
+                    shape,
+                    offset,
+                    surface,
+                    chunk,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { hash, colliders })
+    }
+
+    /// The colliders of the file, if it was baked from a level with this [`level_hash`]
+    pub fn from_bytes_matching(bytes: &[u8], hash: u64) -> Result<Vec<TileCollider>, BakeError> {
+        let baked = Self::from_bytes(bytes)?;
+        if baked.hash != hash {
+            return Err(BakeError::Stale);
+        }
+        Ok(baked.colliders)
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.0.extend(v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.0.extend(v.to_le_bytes());
+    }
+
+    fn vec2(&mut self, v: Vec2) {
+        self.0.extend(v.x.to_le_bytes());
+        self.0.extend(v.y.to_le_bytes());
+    }
+
+    fn triangle(&mut self, tri: &[Vec2; 3]) {
+        tri.iter().for_each(|&p| self.vec2(p));
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BakeError> {
+        if self.0.len() < n {
+            return Err(BakeError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, BakeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, BakeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, BakeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, BakeError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, BakeError> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    fn triangle(&mut self) -> Result<[Vec2; 3], BakeError> {
+        Ok([self.vec2()?, self.vec2()?, self.vec2()?])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn baked() -> BakedColliders {
+        let tri = [Vec2::ZERO, Vec2::X, Vec2::Y];
+        let square = vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        let hole = vec![Vec2::splat(0.25), Vec2::splat(0.5), Vec2::new(0.25, 0.5)];
+        let shapes = [
+            ColliderShape::Triangle(tri),
+            ColliderShape::Compound(vec![tri, [Vec2::X, Vec2::ONE, Vec2::Y]]),
+            ColliderShape::Polyline(vec![square.clone(), hole]),
+            ColliderShape::ConvexDecomposition(vec![square, tri.to_vec()]),
+        ];
+        let surfaces = [Surface::Normal, Surface::Ice, Surface::Sticky, Surface::Bouncy, Surface::NoGrapple];
+
+        BakedColliders {
+            hash: 0x0123_4567_89ab_cdef,
+            colliders: shapes
+                .into_iter()
+                .zip(surfaces)
+                .enumerate()
+                .map(|(i, (shape, surface))| TileCollider {
+                    shape,
+                    offset: Vec2::new(i as f32 * 16.0, -8.5),
+                    surface,
+                    chunk: (i, 2),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trips_every_shape() {
+        let baked = baked();
+        let read = BakedColliders::from_bytes(&baked.to_bytes()).unwrap();
+        assert_eq!(read.hash, baked.hash);
+        assert_eq!(read.colliders, baked.colliders);
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = baked().to_bytes();
+        for len in 0..bytes.len() {
+            assert!(
+                matches!(BakedColliders::from_bytes(&bytes[..len]), Err(BakeError::Truncated)),
+                "accepted the first {len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = baked().to_bytes();
+        bytes[..4].copy_from_slice(b"\x89PNG");
+        assert!(matches!(BakedColliders::from_bytes(&bytes), Err(BakeError::Magic)));
+    }
+
+    #[test]
+    fn rejects_other_layout_versions() {
+        let mut bytes = baked().to_bytes();
+        bytes[3] = MAGIC[3] - 1;
+        assert!(matches!(
+            BakedColliders::from_bytes(&bytes),
+            Err(BakeError::Version(v)) if v == MAGIC[3] - 1
+        ));
+    }
+
+    #[test]
+    fn rejects_other_hashes() {
+        let baked = baked();
+        let bytes = baked.to_bytes();
+        assert_eq!(BakedColliders::from_bytes_matching(&bytes, baked.hash).unwrap(), baked.colliders);
+        assert!(matches!(
+            BakedColliders::from_bytes_matching(&bytes, baked.hash + 1),
+            Err(BakeError::Stale)
+        ));
+    }
+}
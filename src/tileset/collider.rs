@@ -0,0 +1,392 @@
+use crate::tileset::load::TileGeometry;
+use crate::tileset::tile::Surface;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use geo::triangulate_spade::SpadeTriangulationConfig;
+use geo::{BoundingRect, Centroid, MultiPolygon, Polygon, TriangulateSpade};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// How the merged tile polygons of a tilemap are turned into colliders
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum ColliderMode {
+    /// One static entity per triangle of the triangulated polygons
+    #[default]
+    Triangles,
+    /// One compound collider of triangles per polygon
+    Compound,
+    /// One polyline per polygon, tracing its outline and holes.
+    /// The fewest shapes, but the inside of a polygon isn't solid.
+    Polyline,
+    /// One compound collider of convex parts per polygon,
+    /// fewer shapes than [`ColliderMode::Compound`] for the same area
+    ConvexDecomposition,
+}
+
+/// The width and height in tiles of the chunks colliders are built in.
+/// Tiles are only merged within a chunk, so editing a tile only rebuilds the colliders of its chunk.
+pub const COLLIDER_CHUNK_SIZE: usize = 32;
+
+/// The version of the collider generation code, part of the hash of baked colliders.
+/// Bump it whenever `grid_to_polys`, [`ColliderMode::build`] or anything they call changes
+/// what colliders a level gets, so that `.colliders` sidecars baked by older code are regenerated.
+pub const COLLIDER_GENERATOR_VERSION: u32 = 2;
+
+/// A collider of a tilemap, relative to its `offset`
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct TileCollider {
+    pub shape: ColliderShape,
+    pub offset: Vec2,
+    pub surface: Surface,
+    /// The chunk of tiles it was built from, see [`COLLIDER_CHUNK_SIZE`]
+    pub chunk: (usize, usize),
+}
+
+/// The parent of the colliders of one chunk of a spawned tilemap
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ColliderChunk {
+    pub map: Entity,
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum ColliderShape {
+    Triangle([Vec2; 3]),
+    Compound(Vec<[Vec2; 3]>),
+    /// Closed loops, one for the exterior and one for each hole
+    Polyline(Vec<Vec<Vec2>>),
+    /// Convex polygons, each counter-clockwise
+    ConvexDecomposition(Vec<Vec<Vec2>>),
+}
+
+impl ColliderShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColliderShape::Triangle(_) => "Triangle",
+            ColliderShape::Compound(_) => "Compound Collider",
+            ColliderShape::Polyline(_) => "Polyline Collider",
+            ColliderShape::ConvexDecomposition(_) => "Convex Collider",
+        }
+    }
+
+    pub fn to_collider(&self) -> Collider {
+        match self {
+            &ColliderShape::Triangle([a, b, c]) => Collider::triangle(a, b, c),
+            ColliderShape::Compound(triangles) => Collider::compound(
+                triangles
+                    .iter()
+                    .map(|&[a, b, c]| (Vec2::ZERO, Rotation::default(), Collider::triangle(a, b, c)))
+                    .collect(),
+            ),
+            ColliderShape::Polyline(loops) => {
+                let (vertices, indices) = loop_edges(loops);
+                Collider::polyline(vertices, Some(indices))
+            }
+            ColliderShape::ConvexDecomposition(parts) => Collider::compound(
+                parts
+                    .iter()
+                    .filter_map(|part| Collider::convex_hull(part.clone()))
+                    .map(|part| (Vec2::ZERO, Rotation::default(), part))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The vertices of closed loops and the edges between them
+fn loop_edges(loops: &[Vec<Vec2>]) -> (Vec<Vec2>, Vec<[u32; 2]>) {
+    let mut vertices = vec![];
+    let mut indices = vec![];
+
+    for ring in loops {
+        let start = vertices.len() as u32;
+        let len = ring.len() as u32;
+        vertices.extend(ring.iter().copied());
+        indices.extend((0..len).map(|i| [start + i, start + (i + 1) % len]));
+    }
+
+    (vertices, indices)
+}
+
+/// A polygon of merged tiles that couldn't be turned into colliders
+#[derive(Debug, Clone, Error)]
+#[error("Could not triangulate the tiles from {min:?} to {max:?}: {reason}")]
+pub struct ColliderError {
+    /// The top-left tile of the polygon's bounding box
+    pub min: (i64, i64),
+    /// The bottom-right tile of the polygon's bounding box
+    pub max: (i64, i64),
+    pub reason: String,
+}
+
+impl ColliderError {
+    fn new(polygon: &Polygon<f32>, geometry: TileGeometry, reason: String) -> Self {
+        let (min, max) = match polygon.bounding_rect() {
+            Some(rect) => {
+                // step a bit inside the box so its edges don't round into the next tile
+                let inset = geometry.tile_size / 4.0;
+                let top_left = Vec2::new(rect.min().x + inset, rect.max().y - inset);
+                let bottom_right = Vec2::new(rect.max().x - inset, rect.min().y + inset);
+                let (a, b) = (geometry.tile_at(top_left), geometry.tile_at(bottom_right));
+                ((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1)))
+            }
+            None => ((0, 0), (0, 0)),
+        };
+
+        Self { min, max, reason }
+    }
+}
+
+/// The triangles of a polygon, relative to `origin`
+fn triangulate(
+    polygon: &Polygon<f32>,
+    origin: Vec2,
+    geometry: TileGeometry,
+) -> Result<Vec<[Vec2; 3]>, ColliderError> {
+    let triangles = polygon
+        .constrained_triangulation(SpadeTriangulationConfig::default())
+        .map_err(|e| ColliderError::new(polygon, geometry, format!("{e:?}")))?;
+
+    Ok(triangles
+        .into_iter()
+        .map(|tri| tri.to_array().map(|p| Vec2::new(p.x, p.y) - origin))
+        .collect())
+}
+
+/// The rings of a polygon relative to `origin`, without geo's repeated closing point
+fn rings(polygon: &Polygon<f32>, origin: Vec2) -> Vec<Vec<Vec2>> {
+    std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .map(|ring| {
+            let mut points = ring.coords().map(|c| Vec2::new(c.x, c.y) - origin).collect::<Vec<_>>();
+            if points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            points
+        })
+        .collect()
+}
+
+/// Whether the corners of a counter-clockwise polygon only ever turn left (or go straight)
+fn is_convex(corners: &[Vec2]) -> bool {
+    let n = corners.len();
+    (0..n).all(|i| {
+        let (a, b, c) = (corners[i], corners[(i + 1) % n], corners[(i + 2) % n]);
+        (b - a).perp_dot(c - b) >= -1e-4
+    })
+}
+
+/// Splits a polygon into convex parts relative to `origin`, each counter-clockwise.
+/// Triangulates it, then merges neighbouring parts as long as the result stays convex (Hertel-Mehlhorn),
+/// so unlike a voxel-based decomposition the parts cover exactly the polygon, holes included.
+fn convex_parts(
+    polygon: &Polygon<f32>,
+    origin: Vec2,
+    geometry: TileGeometry,
+) -> Result<Vec<Vec<Vec2>>, ColliderError> {
+    let mut parts: Vec<Vec<Vec2>> = triangulate(polygon, origin, geometry)?
+        .into_iter()
+        .map(|[a, b, c]| if (b - a).perp_dot(c - a) < 0.0 { vec![a, c, b] } else { vec![a, b, c] })
+        .collect();
+
+    // the parts of a triangulation share edges in opposite directions
+    let merge = |p: &[Vec2], q: &[Vec2]| -> Option<Vec<Vec2>> {
+        let (np, nq) = (p.len(), q.len());
+        for i in 0..np {
+            let (a, b) = (p[i], p[(i + 1) % np]);
+            let Some(j) = (0..nq).find(|&j| q[j] == b && q[(j + 1) % nq] == a) else {
+                continue;
+            };
+            // all of p from b around to a, then the rest of q from after a around to before b
+            let merged: Vec<Vec2> = (0..np)
+                .map(|k| p[(i + 1 + k) % np])
+                .chain((2..nq).map(|k| q[(j + k) % nq]))
+                .collect();
+            return is_convex(&merged).then_some(merged);
+        }
+        None
+    };
+
+    'merging: loop {
+        for i in 0..parts.len() {
+            for j in i + 1..parts.len() {
+                if let Some(merged) = merge(&parts[i], &parts[j]) {
+                    parts[i] = merged;
+                    parts.swap_remove(j);
+                    continue 'merging;
+                }
+            }
+        }
+        break;
+    }
+
+    Ok(parts)
+}
+
+impl ColliderMode {
+    /// Builds the colliders for the polygons of each surface of `chunk`
+    pub fn build(
+        self,
+        layers: Vec<(Surface, MultiPolygon<f32>)>,
+        geometry: TileGeometry,
+        chunk: (usize, usize),
+    ) -> Result<Vec<TileCollider>, ColliderError> {
+        let mut colliders = vec![];
+
+        for (surface, polygons) in layers {
+            for polygon in polygons.iter() {
+                let Some(centroid) = polygon.centroid() else {
+                    continue;
+                };
+                let offset = Vec2::new(centroid.x(), centroid.y());
+
+                match self {
+                    ColliderMode::Triangles => {
+                        colliders.extend(triangulate(polygon, Vec2::ZERO, geometry)?.into_iter().map(|tri| {
+                            let center = (tri[0] + tri[1] + tri[2]) / 3.0;
+                            TileCollider {
+                                shape: ColliderShape::Triangle(tri.map(|p| p - center)),
+                                offset: center,
+                                surface,
+                                chunk,
+                            }
+                        }));
+                    }
+                    ColliderMode::Compound => colliders.push(TileCollider {
+                        shape: ColliderShape::Compound(triangulate(polygon, offset, geometry)?),
+                        offset,
+                        surface,
+                        chunk,
+                    }),
+                    ColliderMode::Polyline => colliders.push(TileCollider {
+                        shape: ColliderShape::Polyline(rings(polygon, offset)),
+                        offset,
+                        surface,
+                        chunk,
+                    }),
+                    ColliderMode::ConvexDecomposition => colliders.push(TileCollider {
+                        shape: ColliderShape::ConvexDecomposition(convex_parts(polygon, offset, geometry)?),
+                        offset,
+                        surface,
+                        chunk,
+                    }),
+                }
+            }
+        }
+
+        Ok(colliders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::grid::Grid;
+    use crate::tileset::load::grid_to_polys;
+    use crate::tileset::tile::Tile;
+    use geo::Area;
+
+    /// A walled room with a ramp of every orientation in its corners and a hole in the middle,
+    /// next to an icy pillar
+    const LEVEL: &str = "\
+XXXXX  I
+X, `X  I
+X   X  I
+X\\ /X  I
+XXXXX  I";
+
+    fn layers() -> (Grid<Tile>, Vec<(Surface, MultiPolygon<f32>)>) {
+        let (grid, surfaces) = Grid::<(Tile, Surface)>::try_from(LEVEL).unwrap().unzip();
+        let layers = grid_to_polys(&grid, &surfaces, TileGeometry::default());
+        (grid, layers)
+    }
+
+    fn shoelace(ring: &[Vec2]) -> f32 {
+        let sum: f32 = ring
+            .iter()
+            .zip(ring.iter().cycle().skip(1))
+            .map(|(a, b)| a.perp_dot(*b))
+            .sum();
+        (sum / 2.0).abs()
+    }
+
+    /// The area of the collider's shape, or of the region enclosed by its outline for polylines
+    fn area(collider: &TileCollider) -> f32 {
+        match &collider.shape {
+            ColliderShape::Polyline(loops) => {
+                let (exterior, holes) = loops.split_first().unwrap();
+                shoelace(exterior) - holes.iter().map(|hole| shoelace(hole)).sum::<f32>()
+            }
+            shape => shape.to_collider().shape().mass_properties(1.0).mass(),
+        }
+    }
+
+    fn assert_covers_polygons(mode: ColliderMode) {
+        let (grid, layers) = layers();
+        let expected: f32 = layers.iter().map(|(_, polygons)| polygons.unsigned_area()).sum();
+
+        let solids = grid.iter().filter(|(_, &t)| t == Tile::Solid).count() as f32;
+        let ramps = grid.iter().filter(|(_, &t)| matches!(t, Tile::Ramp(_))).count() as f32;
+        let tile_area = TileGeometry::default().tile_size.powi(2);
+        assert!((expected - (solids + ramps / 2.0) * tile_area).abs() < 1e-2);
+
+        let colliders = mode.build(layers, TileGeometry::default(), (0, 0)).unwrap();
+        let actual: f32 = colliders.iter().map(area).sum();
+        assert!(
+            (actual - expected).abs() < expected * 1e-4,
+            "{mode:?} covers {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn triangles_cover_polygons() {
+        assert_covers_polygons(ColliderMode::Triangles);
+    }
+
+    #[test]
+    fn compound_covers_polygons() {
+        assert_covers_polygons(ColliderMode::Compound);
+    }
+
+    #[test]
+    fn polyline_encloses_polygons() {
+        assert_covers_polygons(ColliderMode::Polyline);
+    }
+
+    #[test]
+    fn convex_decomposition_covers_polygons() {
+        assert_covers_polygons(ColliderMode::ConvexDecomposition);
+    }
+
+    #[test]
+    fn polyline_keeps_the_hole() {
+        let (_, layers) = layers();
+        let colliders = ColliderMode::Polyline.build(layers, TileGeometry::default(), (0, 0)).unwrap();
+        let holes: usize = colliders
+            .iter()
+            .map(|collider| match &collider.shape {
+                ColliderShape::Polyline(loops) => loops.len() - 1,
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(holes, 1);
+    }
+
+    #[test]
+    fn convex_decomposition_merges_triangles_into_convex_parts() {
+        let (_, layers) = layers();
+        let triangles = ColliderMode::Triangles.build(layers.clone(), TileGeometry::default(), (0, 0)).unwrap();
+        let colliders = ColliderMode::ConvexDecomposition.build(layers, TileGeometry::default(), (0, 0)).unwrap();
+
+        let parts: Vec<_> = colliders
+            .iter()
+            .flat_map(|collider| match &collider.shape {
+                ColliderShape::ConvexDecomposition(parts) => parts.clone(),
+                _ => vec![],
+            })
+            .collect();
+        assert!(parts.iter().all(|part| is_convex(part)));
+        assert!(parts.len() < triangles.len());
+    }
+}
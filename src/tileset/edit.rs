@@ -0,0 +1,228 @@
+use crate::tileset::collider::{ColliderChunk, COLLIDER_CHUNK_SIZE};
+use crate::tileset::grid::Connectivity;
+use crate::tileset::load::{spawn_collider, TileGridAsset, TileGridSettings};
+use crate::tileset::render::{ChunkMaterials, TileChunk, TileChunkBuilder};
+use crate::tileset::tile::{Surface, Tile};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+/// The live tiles of a spawned tilemap, inserted next to its `Handle<TileGridAsset>`.
+///
+/// Starts as a copy of the asset and is changed with [`TileMapCommands`],
+/// after which only the chunks around the changed tiles are rebuilt.
+#[derive(Component, Debug, Clone)]
+pub struct TileMap {
+    pub grid: TileGridAsset,
+    /// Collider chunks to rebuild, see [`COLLIDER_CHUNK_SIZE`]
+    dirty_colliders: HashSet<(usize, usize)>,
+    /// Tiles whose sprite may have changed, including the neighbours of edited tiles for autotiling
+    dirty_tiles: HashSet<(usize, usize)>,
+}
+
+impl TileMap {
+    pub fn new(grid: TileGridAsset) -> Self {
+        Self {
+            grid,
+            dirty_colliders: default(),
+            dirty_tiles: default(),
+        }
+    }
+
+    /// Changes the tile at (x, y), keeping its surface.
+    /// Returns the previous tile, or `None` if (x, y) is outside the map.
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: Tile) -> Option<Tile> {
+        let old = self.grid.grid.set(x, y, tile)?;
+        if old != tile {
+            self.mark_dirty(x, y);
+        }
+        Some(old)
+    }
+
+    /// Changes the surface of the tile at (x, y).
+    /// Returns the previous surface, or `None` if (x, y) is outside the map.
+    pub fn set_surface(&mut self, x: usize, y: usize, surface: Surface) -> Option<Surface> {
+        let old = self.grid.surfaces.set(x, y, surface)?;
+        if old != surface {
+            self.mark_dirty(x, y);
+        }
+        Some(old)
+    }
+
+    fn mark_dirty(&mut self, x: usize, y: usize) {
+        self.dirty_colliders.insert((x / COLLIDER_CHUNK_SIZE, y / COLLIDER_CHUNK_SIZE));
+        self.dirty_tiles.insert((x, y));
+        let neighbours = self.grid.grid.neighbours(x, y, Connectivity::Eight).map(|(pos, _)| pos);
+        self.dirty_tiles.extend(neighbours);
+    }
+
+    fn is_dirty(&self) -> bool {
+        !self.dirty_colliders.is_empty() || !self.dirty_tiles.is_empty()
+    }
+
+    /// The tile chunks of `chunk_size` by `chunk_size` tiles holding a dirty tile
+    fn dirty_tile_chunks(&self, chunk_size: usize) -> HashSet<(usize, usize)> {
+        self.dirty_tiles
+            .iter()
+            .map(|&(x, y)| (x / chunk_size, y / chunk_size))
+            .collect()
+    }
+}
+
+/// Edits the tiles of a spawned tilemap, see [`TileMap`]
+pub trait TileMapCommands {
+    /// Sets the tile at (x, y) of this tilemap, keeping its surface
+    fn set_tile(&mut self, x: usize, y: usize, tile: Tile) -> &mut Self;
+
+    /// Sets the surface of the tile at (x, y) of this tilemap
+    fn set_surface(&mut self, x: usize, y: usize, surface: Surface) -> &mut Self;
+}
+
+impl TileMapCommands for EntityCommands<'_> {
+    fn set_tile(&mut self, x: usize, y: usize, tile: Tile) -> &mut Self {
+        self.add(move |entity: Entity, world: &mut World| {
+            edit_map(world, entity, |map| map.set_tile(x, y, tile).map(drop), (x, y));
+        })
+    }
+
+    fn set_surface(&mut self, x: usize, y: usize, surface: Surface) -> &mut Self {
+        self.add(move |entity: Entity, world: &mut World| {
+            edit_map(world, entity, |map| map.set_surface(x, y, surface).map(drop), (x, y));
+        })
+    }
+}
+
+fn edit_map(world: &mut World, entity: Entity, edit: impl FnOnce(&mut TileMap) -> Option<()>, (x, y): (usize, usize)) {
+    let Some(mut map) = world.get_mut::<TileMap>(entity) else {
+        warn!("{entity} is not a spawned tilemap");
+        return;
+    };
+
+    if edit(&mut map).is_none() {
+        warn!("tile ({x}, {y}) is outside tilemap {entity}");
+    }
+}
+
+/// Rebuilds the colliders and tiles of the chunks changed since the last frame
+pub(crate) fn rebuild_dirty_chunks(
+    mut builder: TileChunkBuilder,
+    mut maps: Query<(Entity, &mut TileMap, &TileGridSettings), Changed<TileMap>>,
+    collider_chunks: Query<(Entity, &ColliderChunk)>,
+    tile_chunks: Query<(Entity, &TileChunk, &ChunkMaterials)>,
+) {
+    for (map, mut tile_map, settings) in maps.iter_mut() {
+        if !tile_map.is_dirty() {
+            continue;
+        }
+
+        // don't let taking the dirty sets count as another change
+        let tile_map = tile_map.bypass_change_detection();
+        let dirty_chunks = tile_map.dirty_tile_chunks(settings.chunk_size.max(1));
+        let dirty_colliders = std::mem::take(&mut tile_map.dirty_colliders);
+        tile_map.dirty_tiles.clear();
+        let grid = &tile_map.grid;
+
+        for (entity, chunk) in collider_chunks.iter() {
+            if chunk.map != map || !dirty_colliders.contains(&(chunk.x, chunk.y)) {
+                continue;
+            }
+
+            match grid.chunk_colliders(chunk.x, chunk.y) {
+                Ok(colliders) => {
+                    builder
+                        .commands
+                        .entity(entity)
+                        .despawn_descendants()
+                        .with_children(|parent| {
+                            for collider in colliders.iter() {
+                                spawn_collider(parent, collider);
+                            }
+                        });
+                }
+                // keep the old colliders rather than leaving a hole
+                Err(e) => error!("could not rebuild collider chunk ({}, {}) of {map}: {e}", chunk.x, chunk.y),
+            }
+        }
+
+        for (entity, &chunk, materials) in tile_chunks.iter() {
+            if chunk.map != map || !dirty_chunks.contains(&(chunk.x, chunk.y)) {
+                continue;
+            }
+
+            builder.commands.entity(entity).despawn_descendants();
+            builder.fill(entity, chunk, materials, grid, settings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::collider::ColliderMode;
+    use crate::tileset::grid::Grid;
+    use crate::tileset::load::TileGeometry;
+
+    /// An empty map of two by two chunks
+    fn empty_map() -> TileMap {
+        let size = 2 * COLLIDER_CHUNK_SIZE;
+        let grid = Grid::filled(size, size, Tile::Air);
+        let surfaces = Grid::filled(size, size, Surface::Normal);
+        TileMap::new(TileGridAsset::new(grid, surfaces, TileGeometry::default(), ColliderMode::default()).unwrap())
+    }
+
+    fn set(set: &[(usize, usize)]) -> HashSet<(usize, usize)> {
+        set.iter().copied().collect()
+    }
+
+    #[test]
+    fn edits_inside_a_chunk_only_dirty_that_chunk() {
+        let mut map = empty_map();
+        map.set_tile(10, 10, Tile::Solid);
+        assert_eq!(map.dirty_colliders, set(&[(0, 0)]));
+        assert_eq!(map.dirty_tile_chunks(COLLIDER_CHUNK_SIZE), set(&[(0, 0)]));
+    }
+
+    #[test]
+    fn edits_at_a_chunk_boundary_dirty_the_neighbouring_tile_chunks() {
+        let last = COLLIDER_CHUNK_SIZE - 1;
+
+        let mut map = empty_map();
+        map.set_tile(last, 40, Tile::Solid);
+        // colliders are only merged within a chunk, but sprites depend on their neighbours
+        assert_eq!(map.dirty_colliders, set(&[(0, 1)]));
+        assert_eq!(map.dirty_tile_chunks(COLLIDER_CHUNK_SIZE), set(&[(0, 1), (1, 1)]));
+
+        let mut map = empty_map();
+        map.set_surface(last, last, Surface::Ice);
+        assert_eq!(map.dirty_colliders, set(&[(0, 0)]));
+        assert_eq!(map.dirty_tile_chunks(COLLIDER_CHUNK_SIZE), set(&[(0, 0), (1, 0), (0, 1), (1, 1)]));
+    }
+
+    #[test]
+    fn unchanged_tiles_stay_clean() {
+        let mut map = empty_map();
+        assert_eq!(map.set_tile(5, 5, Tile::Air), Some(Tile::Air));
+        assert_eq!(map.set_surface(5, 5, Surface::Normal), Some(Surface::Normal));
+        assert_eq!(map.set_tile(100, 5, Tile::Solid), None);
+        assert!(!map.is_dirty());
+    }
+
+    #[test]
+    fn rebuilding_clears_the_dirty_chunks() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<ColorMaterial>()
+            .init_asset::<TextureAtlasLayout>()
+            .add_systems(Update, rebuild_dirty_chunks);
+
+        let mut map = empty_map();
+        map.set_tile(COLLIDER_CHUNK_SIZE, 0, Tile::Solid);
+        let entity = app.world_mut().spawn((map, TileGridSettings::default())).id();
+        app.update();
+
+        let map = app.world().get::<TileMap>(entity).unwrap();
+        assert!(!map.is_dirty());
+        assert_eq!(map.grid.grid.get(COLLIDER_CHUNK_SIZE, 0), Some(&Tile::Solid));
+    }
+}
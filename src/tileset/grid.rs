@@ -1,32 +1,126 @@
 use bevy::prelude::Reflect;
+use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
+use std::iter::FusedIterator;
 
+/// A rectangular grid, stored row by row
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Reflect)]
 pub struct Grid<T> {
-    grid: Vec<Vec<T>>,
+    cells: Vec<T>,
     pub w: usize,
     pub h: usize,
 }
 
+/// A rectangle of cells, `w` by `h` starting at (`x`, `y`)
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Reflect)]
+pub struct GridRect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+/// Which cells count as neighbours
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Reflect)]
+pub enum Connectivity {
+    /// The cells sharing an edge
+    #[default]
+    Four,
+    /// The cells sharing an edge or a corner
+    Eight,
+}
+
+const OFFSETS_4: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const OFFSETS_8: [(i64, i64); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+impl Connectivity {
+    pub fn offsets(self) -> &'static [(i64, i64)] {
+        match self {
+            Connectivity::Four => &OFFSETS_4,
+            Connectivity::Eight => &OFFSETS_8,
+        }
+    }
+}
+
+impl GridRect {
+    pub fn new(x: usize, y: usize, w: usize, h: usize) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.w).contains(&x) && (self.y..self.y + self.h).contains(&y)
+    }
+
+    pub fn area(&self) -> usize {
+        self.w * self.h
+    }
+}
+
 impl<T> Grid<T> {
-    pub fn new(grid: Vec<Vec<T>>) -> Self {
-        let h = grid.len();
-        let w = grid.first().map(Vec::len).unwrap_or(0);
-        Self { grid, w, h }
+    /// # Panics
+    /// If the rows don't all have the same length
+    pub fn new(rows: Vec<Vec<T>>) -> Self {
+        let h = rows.len();
+        let w = rows.first().map(Vec::len).unwrap_or(0);
+        assert!(rows.iter().all(|row| row.len() == w), "grid rows must all have the same length");
+
+        Self {
+            cells: rows.into_iter().flatten().collect(),
+            w,
+            h,
+        }
+    }
+
+    /// A `w` by `h` grid from its cells, row by row
+    ///
+    /// # Panics
+    /// If there aren't exactly `w * h` cells
+    pub fn from_vec(w: usize, h: usize, cells: Vec<T>) -> Self {
+        assert_eq!(cells.len(), w * h, "a {w}x{h} grid needs {} cells", w * h);
+        Self { cells, w, h }
+    }
+
+    pub fn filled(w: usize, h: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::from_vec(w, h, vec![value; w * h])
+    }
+
+    /// The whole grid as a [`GridRect`]
+    pub fn rect(&self) -> GridRect {
+        GridRect::new(0, 0, self.w, self.h)
+    }
+
+    #[inline]
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.w && y < self.h).then(|| y * self.w + x)
     }
 
     #[inline]
     pub fn get(&self, x: usize, y: usize) -> Option<&T> {
-        self.grid.get(y).and_then(|y| y.get(x))
+        self.index(x, y).map(|i| &self.cells[i])
     }
 
     #[inline]
-    #[allow(dead_code)]
     pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
-        self.grid.get_mut(y).and_then(|y| y.get_mut(x))
+        self.index(x, y).map(|i| &mut self.cells[i])
+    }
+
+    /// Replaces the cell at (x, y), returning the old value
+    pub fn set(&mut self, x: usize, y: usize, value: T) -> Option<T> {
+        self.get_mut(x, y).map(|cell| std::mem::replace(cell, value))
     }
 
-    #[allow(dead_code)]
     pub fn get_i(&self, x: i64, y: i64) -> Option<&T> {
         if x < 0 || y < 0 {
             None
@@ -35,44 +129,214 @@ impl<T> Grid<T> {
         }
     }
 
-    #[allow(dead_code)]
     pub fn get_cycle(&self, mut x: i64, mut y: i64) -> Option<&T> {
         x = x.rem_euclid(self.w as i64);
         y = y.rem_euclid(self.h as i64);
         self.get(x as usize, y as usize)
     }
 
-    #[allow(dead_code)]
     pub fn positions<FN: Fn(&T) -> bool>(&self, predicate: FN) -> Vec<(usize, usize)> {
-        let mut pos = vec![];
-        for (y, row) in self.grid.iter().enumerate() {
-            for (x, item) in row.iter().enumerate() {
-                if predicate(item) {
-                    pos.push((x, y));
+        self.iter()
+            .filter(|(_, item)| predicate(item))
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    pub fn iter(&self) -> GridIter<'_, T> {
+        self.view_unchecked(self.rect()).iter()
+    }
+
+    /// The rows of the grid, top to bottom
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[T]> + ExactSizeIterator {
+        (0..self.h).map(|y| &self.cells[y * self.w..(y + 1) * self.w])
+    }
+
+    pub fn map<X, FN: Fn(T) -> X>(self, func: FN) -> Grid<X> {
+        Grid::from_vec(self.w, self.h, self.cells.into_iter().map(func).collect())
+    }
+
+    /// The in-bounds neighbours of (x, y) and their positions
+    pub fn neighbours(
+        &self,
+        x: usize,
+        y: usize,
+        connectivity: Connectivity,
+    ) -> impl Iterator<Item = ((usize, usize), &T)> {
+        connectivity.offsets().iter().filter_map(move |&(dx, dy)| {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            self.get_i(nx, ny).map(|item| ((nx as usize, ny as usize), item))
+        })
+    }
+
+    /// The in-bounds edge neighbours of (x, y): north, east, south, west
+    pub fn neighbours4(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.neighbours(x, y, Connectivity::Four)
+    }
+
+    /// The in-bounds edge and corner neighbours of (x, y), clockwise from north
+    pub fn neighbours8(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.neighbours(x, y, Connectivity::Eight)
+    }
+
+    /// The cells connected to (x, y) that match `predicate`, in breadth-first order.
+    /// Empty if (x, y) itself doesn't match.
+    pub fn flood_fill(
+        &self,
+        x: usize,
+        y: usize,
+        connectivity: Connectivity,
+        predicate: impl Fn(&T) -> bool,
+    ) -> Vec<(usize, usize)> {
+        self.flood_fill_within(x, y, self.rect(), connectivity, predicate)
+    }
+
+    /// Like [`Grid::flood_fill`], but never leaves `bounds`
+    pub fn flood_fill_within(
+        &self,
+        x: usize,
+        y: usize,
+        bounds: GridRect,
+        connectivity: Connectivity,
+        predicate: impl Fn(&T) -> bool,
+    ) -> Vec<(usize, usize)> {
+        let mut seen = Grid::filled(self.w, self.h, false);
+        self.flood_fill_unseen(x, y, bounds, connectivity, predicate, &mut seen)
+    }
+
+    /// Like [`Grid::flood_fill_within`], but skips the cells already marked in `seen` and marks
+    /// the ones it fills, so repeated fills over the same grid can share one buffer.
+    /// Empty if (x, y) doesn't match or is already seen.
+    ///
+    /// # Panics
+    /// If `seen` isn't the same size as the grid
+    pub fn flood_fill_unseen(
+        &self,
+        x: usize,
+        y: usize,
+        bounds: GridRect,
+        connectivity: Connectivity,
+        predicate: impl Fn(&T) -> bool,
+        seen: &mut Grid<bool>,
+    ) -> Vec<(usize, usize)> {
+        assert_eq!((self.w, self.h), (seen.w, seen.h), "the seen grid must be the same size");
+        let inside = |x, y| bounds.contains(x, y) && self.get(x, y).is_some_and(&predicate);
+        if seen.get(x, y) != Some(&false) || !inside(x, y) {
+            return vec![];
+        }
+
+        let mut queue = VecDeque::from([(x, y)]);
+        let mut filled = vec![];
+        seen.set(x, y, true);
+
+        while let Some((x, y)) = queue.pop_front() {
+            filled.push((x, y));
+            for ((nx, ny), _) in self.neighbours(x, y, connectivity) {
+                if !seen.cells[ny * self.w + nx] && inside(nx, ny) {
+                    seen.cells[ny * self.w + nx] = true;
+                    queue.push_back((nx, ny));
                 }
             }
         }
-        pos
+
+        filled
     }
 
-    #[allow(dead_code)]
-    pub fn iter(&self) -> GridIter<T> {
-        GridIter {
-            grid: self,
-            x: 0,
-            y: 0,
+    /// Labels each group of connected cells matching `predicate` with its own index,
+    /// numbered in reading order of their first cell. Returns the labels and the number of groups.
+    pub fn label_components(
+        &self,
+        connectivity: Connectivity,
+        predicate: impl Fn(&T) -> bool,
+    ) -> (Grid<Option<usize>>, usize) {
+        let mut labels = Grid::filled(self.w, self.h, None);
+        let mut seen = Grid::filled(self.w, self.h, false);
+        let mut count = 0;
+
+        for y in 0..self.h {
+            for x in 0..self.w {
+                let component = self.flood_fill_unseen(x, y, self.rect(), connectivity, &predicate, &mut seen);
+                if component.is_empty() {
+                    continue;
+                }
+
+                for (cx, cy) in component {
+                    labels.set(cx, cy, Some(count));
+                }
+                count += 1;
+            }
         }
+
+        (labels, count)
     }
 
-    #[allow(dead_code)]
-    pub fn map<X, FN: Fn(T) -> X>(self, func: FN) -> Grid<X> {
-        self.grid
-            .into_iter()
-            .map(|x| x.into_iter().map(&func))
-            .collect()
+    /// A read-only view of the cells in `rect`, or `None` if it doesn't fit in the grid
+    pub fn view(&self, rect: GridRect) -> Option<GridView<'_, T>> {
+        (rect.x + rect.w <= self.w && rect.y + rect.h <= self.h).then(|| self.view_unchecked(rect))
+    }
+
+    fn view_unchecked(&self, rect: GridRect) -> GridView<'_, T> {
+        GridView { grid: self, rect }
+    }
+
+    /// A copy of the cells in `rect`, or `None` if it doesn't fit in the grid
+    pub fn sub_grid(&self, rect: GridRect) -> Option<Grid<T>>
+    where
+        T: Clone,
+    {
+        self.view(rect).map(|view| view.to_grid())
+    }
+
+    /// Resizes the grid to `w` by `h`, keeping the top-left cells and filling new ones with `fill`
+    pub fn resize(&mut self, w: usize, h: usize, fill: T)
+    where
+        T: Clone,
+    {
+        let mut cells = Vec::with_capacity(w * h);
+        for y in 0..h {
+            cells.extend((0..w).map(|x| self.get(x, y).unwrap_or(&fill).clone()));
+        }
+        *self = Self::from_vec(w, h, cells);
+    }
+
+    /// Inserts a row filled with `fill` before row `y`, moving the rows below down
+    ///
+    /// # Panics
+    /// If `y > h`
+    pub fn insert_row(&mut self, y: usize, fill: T)
+    where
+        T: Clone,
+    {
+        assert!(y <= self.h, "row {y} is outside a grid of height {}", self.h);
+        let at = y * self.w;
+        self.cells.splice(at..at, vec![fill; self.w]);
+        self.h += 1;
+    }
+
+    /// Inserts a column filled with `fill` before column `x`, moving the columns to its right
+    ///
+    /// # Panics
+    /// If `x > w`
+    pub fn insert_col(&mut self, x: usize, fill: T)
+    where
+        T: Clone,
+    {
+        assert!(x <= self.w, "column {x} is outside a grid of width {}", self.w);
+        // insert from the bottom row up so the earlier indices stay valid
+        for y in (0..self.h).rev() {
+            self.cells.insert(y * self.w + x, fill.clone());
+        }
+        self.w += 1;
+    }
+
+    /// Pairs up the cells of two grids of the same size, the reverse of [`Grid::unzip`]
+    ///
+    /// # Panics
+    /// If the grids aren't the same size
+    pub fn zip<U>(self, other: Grid<U>) -> Grid<(T, U)> {
+        assert_eq!((self.w, self.h), (other.w, other.h), "zipped grids must be the same size");
+        Grid::from_vec(self.w, self.h, self.cells.into_iter().zip(other.cells).collect())
     }
 
-    #[allow(dead_code)]
     pub fn try_from_iter<E, IT, TIT>(iter: TIT) -> Result<Self, (usize, usize, E)>
     where
         IT: IntoIterator<Item = Result<T, E>>,
@@ -90,30 +354,147 @@ impl<T> Grid<T> {
             .collect::<Result<_, _>>()
             .map(Self::new)
     }
+
+    /// Like [`Grid::try_from_iter`], but keeps going after the first error and returns all of them.
+    pub fn try_from_iter_all<E, IT, TIT>(iter: TIT) -> Result<Self, Vec<(usize, usize, E)>>
+    where
+        IT: IntoIterator<Item = Result<T, E>>,
+        TIT: IntoIterator<Item = IT>,
+    {
+        let mut errors = vec![];
+        let grid = iter
+            .into_iter()
+            .enumerate()
+            .map(|(y, inner)| {
+                inner
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(x, item)| {
+                        item.map_err(|e| errors.push((x, y, e))).ok()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(Self::new(grid))
+        } else {
+            Err(errors)
+        }
+    }
 }
 
-pub struct GridIter<'a, T> {
+impl<A, B> Grid<(A, B)> {
+    /// Splits a grid of pairs into two layers
+    pub fn unzip(self) -> (Grid<A>, Grid<B>) {
+        let (a, b) = self.cells.into_iter().unzip();
+        (Grid::from_vec(self.w, self.h, a), Grid::from_vec(self.w, self.h, b))
+    }
+}
+
+/// A rectangle of a [`Grid`], with positions relative to its top-left corner
+#[derive(Debug)]
+pub struct GridView<'a, T> {
     grid: &'a Grid<T>,
-    x: usize,
-    y: usize,
+    rect: GridRect,
+}
+
+// derived `Clone` and `Copy` would needlessly require `T: Copy`
+impl<T> Clone for GridView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for GridView<'_, T> {}
+
+impl<'a, T> GridView<'a, T> {
+    pub fn rect(&self) -> GridRect {
+        self.rect
+    }
+
+    pub fn w(&self) -> usize {
+        self.rect.w
+    }
+
+    pub fn h(&self) -> usize {
+        self.rect.h
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a T> {
+        if x < self.rect.w && y < self.rect.h {
+            self.grid.get(self.rect.x + x, self.rect.y + y)
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> GridIter<'a, T> {
+        GridIter {
+            view: *self,
+            front: 0,
+            back: self.rect.area(),
+        }
+    }
+
+    pub fn to_grid(&self) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Grid::from_vec(self.rect.w, self.rect.h, self.iter().map(|(_, item)| item.clone()).collect())
+    }
+}
+
+/// The cells of a grid or view and their positions, row by row
+pub struct GridIter<'a, T> {
+    view: GridView<'a, T>,
+    /// Index of the next cell from the front, in reading order
+    front: usize,
+    /// One past the index of the next cell from the back
+    back: usize,
+}
+
+impl<'a, T> GridIter<'a, T> {
+    fn item(&self, i: usize) -> ((usize, usize), &'a T) {
+        let (x, y) = (i % self.view.rect.w, i / self.view.rect.w);
+        ((x, y), self.view.get(x, y).expect("iterator index is inside the view"))
+    }
 }
 
 impl<'a, T> Iterator for GridIter<'a, T> {
     type Item = ((usize, usize), &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let item = self.grid.get(self.x, self.y).map(|x| ((self.x, self.y), x));
+        if self.front == self.back {
+            return None;
+        }
 
-        self.x += 1;
-        if self.x == self.grid.w {
-            self.y += 1;
-            self.x = 0;
+        let item = self.item(self.front);
+        self.front += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for GridIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
         }
 
-        item
+        self.back -= 1;
+        Some(self.item(self.back))
     }
 }
 
+impl<'a, T> ExactSizeIterator for GridIter<'a, T> {}
+
+impl<'a, T> FusedIterator for GridIter<'a, T> {}
+
 impl<T, IT> FromIterator<IT> for Grid<T>
 where
     IT: IntoIterator<Item = T>,
@@ -156,7 +537,7 @@ where
 
 impl<T: Display> Display for Grid<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for row in self.grid.iter() {
+        for row in self.rows() {
             for col in row.iter() {
                 write!(f, "{}", col)?;
             }
@@ -166,3 +547,245 @@ impl<T: Display> Display for Grid<T> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3x2 grid numbered 0..6 in reading order
+    fn numbered() -> Grid<usize> {
+        Grid::new(vec![vec![0, 1, 2], vec![3, 4, 5]])
+    }
+
+    fn checkerboard(w: usize, h: usize) -> Grid<bool> {
+        Grid::from_vec(w, h, (0..w * h).map(|i| i % w % 2 == i / w % 2).collect())
+    }
+
+    #[test]
+    fn stores_cells_row_by_row() {
+        let grid = numbered();
+        assert_eq!((grid.w, grid.h), (3, 2));
+        assert_eq!(grid.cells, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(grid, Grid::from_vec(3, 2, vec![0, 1, 2, 3, 4, 5]));
+        assert_eq!(grid.get(2, 0), Some(&2));
+        assert_eq!(grid.get(0, 1), Some(&3));
+        assert_eq!(grid.rows().collect::<Vec<_>>(), vec![&[0, 1, 2][..], &[3, 4, 5][..]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_ragged_rows() {
+        Grid::new(vec![vec![0, 1], vec![2]]);
+    }
+
+    #[test]
+    fn iterates_from_both_ends() {
+        let grid = numbered();
+        let forward: Vec<_> = grid.iter().map(|(pos, &v)| (pos, v)).collect();
+        assert_eq!(
+            forward,
+            vec![((0, 0), 0), ((1, 0), 1), ((2, 0), 2), ((0, 1), 3), ((1, 1), 4), ((2, 1), 5)]
+        );
+
+        let backward: Vec<_> = grid.iter().rev().map(|(_, &v)| v).collect();
+        assert_eq!(backward, vec![5, 4, 3, 2, 1, 0]);
+
+        let mut iter = grid.iter();
+        assert_eq!(iter.next().map(|(_, &v)| v), Some(0));
+        assert_eq!(iter.next_back().map(|(_, &v)| v), Some(5));
+        assert_eq!(iter.next_back().map(|(_, &v)| v), Some(4));
+        assert_eq!(iter.map(|(_, &v)| v).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn len_shrinks_with_partial_iteration() {
+        let grid = numbered();
+        let mut iter = grid.iter();
+        assert_eq!(iter.len(), 6);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 4);
+        iter.by_ref().take(4).for_each(drop);
+        assert_eq!(iter.len(), 0);
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+    }
+
+    #[test]
+    fn out_of_range_get_and_set() {
+        let mut grid = numbered();
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(grid.get(0, 2), None);
+        assert_eq!(grid.get_i(-1, 0), None);
+        assert_eq!(grid.get_mut(3, 1), None);
+        assert_eq!(grid.set(3, 0, 9), None);
+        assert_eq!(grid.set(0, 2, 9), None);
+        assert_eq!(grid, numbered());
+
+        assert_eq!(grid.set(1, 1, 9), Some(4));
+        assert_eq!(grid.get(1, 1), Some(&9));
+        assert_eq!(grid.get_cycle(-1, 2), Some(&2));
+    }
+
+    #[test]
+    fn view_and_sub_grid() {
+        let grid = numbered();
+        let view = grid.view(GridRect::new(1, 0, 2, 2)).unwrap();
+        assert_eq!((view.w(), view.h()), (2, 2));
+        assert_eq!(view.get(0, 1), Some(&4));
+        assert_eq!(view.get(2, 0), None);
+        assert_eq!(view.iter().map(|(_, &v)| v).collect::<Vec<_>>(), vec![1, 2, 4, 5]);
+        assert_eq!(view.iter().next_back().map(|(pos, _)| pos), Some((1, 1)));
+
+        assert!(grid.view(GridRect::new(2, 0, 2, 1)).is_none());
+        assert!(grid.view(GridRect::new(0, 1, 1, 2)).is_none());
+        assert_eq!(grid.sub_grid(GridRect::new(0, 1, 2, 1)), Some(Grid::new(vec![vec![3, 4]])));
+        assert_eq!(grid.sub_grid(grid.rect()), Some(grid.clone()));
+        assert_eq!(grid.sub_grid(GridRect::new(0, 0, 4, 1)), None);
+    }
+
+    #[test]
+    fn resize_grows_and_shrinks() {
+        let mut grid = numbered();
+        grid.resize(4, 3, 9);
+        assert_eq!(grid, Grid::new(vec![vec![0, 1, 2, 9], vec![3, 4, 5, 9], vec![9, 9, 9, 9]]));
+
+        grid.resize(2, 1, 9);
+        assert_eq!(grid, Grid::new(vec![vec![0, 1]]));
+
+        grid.resize(0, 0, 9);
+        assert_eq!((grid.w, grid.h), (0, 0));
+        assert_eq!(grid.iter().len(), 0);
+    }
+
+    #[test]
+    fn insert_rows() {
+        let mut grid = numbered();
+        grid.insert_row(0, 7);
+        assert_eq!(grid, Grid::new(vec![vec![7, 7, 7], vec![0, 1, 2], vec![3, 4, 5]]));
+
+        let mut grid = numbered();
+        grid.insert_row(grid.h, 7);
+        assert_eq!(grid, Grid::new(vec![vec![0, 1, 2], vec![3, 4, 5], vec![7, 7, 7]]));
+    }
+
+    #[test]
+    fn insert_cols() {
+        let mut grid = numbered();
+        grid.insert_col(0, 7);
+        assert_eq!(grid, Grid::new(vec![vec![7, 0, 1, 2], vec![7, 3, 4, 5]]));
+
+        let mut grid = numbered();
+        grid.insert_col(grid.w, 7);
+        assert_eq!(grid, Grid::new(vec![vec![0, 1, 2, 7], vec![3, 4, 5, 7]]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_row_past_the_end() {
+        let mut grid = numbered();
+        grid.insert_row(3, 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_col_past_the_end() {
+        let mut grid = numbered();
+        grid.insert_col(4, 7);
+    }
+
+    #[test]
+    fn zip_and_unzip() {
+        let letters = Grid::new(vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f']]);
+        let zipped = numbered().zip(letters.clone());
+        assert_eq!(zipped.get(1, 1), Some(&(4, 'e')));
+        assert_eq!(zipped.unzip(), (numbered(), letters));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zip_different_sizes() {
+        numbered().zip(Grid::filled(2, 3, 0));
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = Grid::from_vec(3, 3, (0..9).collect());
+        let values = |it: &mut dyn Iterator<Item = ((usize, usize), &i32)>| it.map(|(_, &v)| v).collect::<Vec<_>>();
+
+        assert_eq!(values(&mut grid.neighbours4(1, 1)), vec![1, 5, 7, 3]);
+        assert_eq!(values(&mut grid.neighbours8(1, 1)), vec![1, 2, 5, 8, 7, 6, 3, 0]);
+        assert_eq!(values(&mut grid.neighbours4(0, 0)), vec![1, 3]);
+        assert_eq!(values(&mut grid.neighbours8(0, 0)), vec![1, 4, 3]);
+        assert_eq!(values(&mut grid.neighbours8(2, 2)), vec![5, 7, 4]);
+        assert_eq!(grid.neighbours4(0, 2).map(|(pos, _)| pos).collect::<Vec<_>>(), vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn flood_fill_within_bounds() {
+        let grid: Grid<char> = ["..#..", "..#..", "....."].iter().map(|row| row.chars()).collect();
+        let open = |c: &char| *c == '.';
+
+        let all = grid.flood_fill(0, 0, Connectivity::Four, open);
+        assert_eq!(all.len(), 13);
+        assert_eq!(all[0], (0, 0));
+        assert!(!all.contains(&(2, 0)));
+
+        // the bottom row is the only way around the wall, so cutting it off splits the two sides
+        let top = GridRect::new(0, 0, 5, 2);
+        let mut left = grid.flood_fill_within(0, 0, top, Connectivity::Four, open);
+        left.sort();
+        assert_eq!(left, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        assert!(grid.flood_fill_within(0, 2, top, Connectivity::Four, open).is_empty());
+        assert!(grid.flood_fill(2, 0, Connectivity::Four, open).is_empty());
+    }
+
+    #[test]
+    fn flood_fill_unseen_skips_seen_cells() {
+        let grid = Grid::filled(3, 1, true);
+        let mut seen = Grid::new(vec![vec![false, true, false]]);
+        assert_eq!(grid.flood_fill_unseen(0, 0, grid.rect(), Connectivity::Four, |&b| b, &mut seen), vec![(0, 0)]);
+        assert!(grid.flood_fill_unseen(0, 0, grid.rect(), Connectivity::Four, |&b| b, &mut seen).is_empty());
+        assert_eq!(seen, Grid::new(vec![vec![true, true, false]]));
+    }
+
+    #[test]
+    fn label_checkerboard_four() {
+        let grid = checkerboard(4, 3);
+        let (labels, count) = grid.label_components(Connectivity::Four, |&b| b);
+
+        // no two matching cells share an edge, so each one is its own component
+        assert_eq!(count, 6);
+        let expected = [
+            [Some(0), None, Some(1), None],
+            [None, Some(2), None, Some(3)],
+            [Some(4), None, Some(5), None],
+        ];
+        assert_eq!(labels, expected.iter().map(|row| row.iter().copied()).collect());
+    }
+
+    #[test]
+    fn label_checkerboard_eight() {
+        let grid = checkerboard(4, 3);
+
+        let (labels, count) = grid.label_components(Connectivity::Eight, |&b| b);
+        assert_eq!(count, 1);
+        assert!(grid.iter().all(|((x, y), &b)| labels.get(x, y) == Some(&b.then_some(0))));
+
+        let (labels, count) = grid.label_components(Connectivity::Eight, |&b| !b);
+        assert_eq!(count, 1);
+        assert_eq!(labels.get(1, 0), Some(&Some(0)));
+        assert_eq!(labels.get(0, 0), Some(&None));
+    }
+
+    #[test]
+    fn label_separate_components_in_reading_order() {
+        let grid: Grid<char> = ["#..#", "#..#", "...#"].iter().map(|row| row.chars()).collect();
+        let (labels, count) = grid.label_components(Connectivity::Four, |&c| c == '#');
+        assert_eq!(count, 2);
+        assert_eq!(labels.get(0, 1), Some(&Some(0)));
+        assert_eq!(labels.get(3, 2), Some(&Some(1)));
+        assert_eq!(labels.get(1, 1), Some(&None));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::tileset::collider::ColliderMode;
+use crate::tileset::grid::Grid;
+use crate::tileset::load::{LevelFiles, TileAnchor, TileGeometry, TileGridAsset, TileGridAssetLoaderError};
+use crate::tileset::tile::{Surface, Tile, UnknownName};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// All levels of an [LDtk](https://ldtk.io/) project, in the order they appear in the project.
+///
+/// Each level is also a labeled sub-asset, so a single level can be loaded
+/// with e.g. `levels/world.ldtk#Level_fall`.
+#[derive(Asset, Debug, Clone, Reflect)]
+pub struct LevelPack {
+    pub names: Vec<String>,
+    pub levels: Vec<Handle<TileGridAsset>>,
+}
+
+#[derive(Debug, Error)]
+pub enum LdtkError {
+    #[error("Could not parse JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Level {0:?} has no layers")]
+    MissingLayers(String),
+    #[error("IntGrid value {value} of layer {layer:?} is not defined")]
+    UnknownValue { layer: String, value: i32 },
+    #[error("IntGrid value {value}: {source}")]
+    Name { value: i32, source: UnknownName },
+    #[error("Layer {layer:?} has {len} cells, expected {expected}")]
+    LayerSize {
+        layer: String,
+        len: usize,
+        expected: usize,
+    },
+}
+
+/// Parses an IntGrid value identifier like `solid`, `ramp_sw` or `solid_no_grapple`
+/// into its tile and surface.
+fn parse_identifier(identifier: &str) -> Result<(Tile, Surface), UnknownName> {
+    if let Ok(tile) = Tile::from_str(identifier) {
+        return Ok((tile, Surface::Normal));
+    }
+
+    identifier
+        .match_indices('_')
+        .find_map(|(i, _)| {
+            let tile = Tile::from_str(&identifier[..i]).ok()?;
+            let surface = Surface::from_str(&identifier[i + 1..]).ok()?;
+            Some((tile, surface))
+        })
+        .ok_or_else(|| UnknownName(identifier.to_string()))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Project {
+    default_grid_size: u32,
+    defs: Definitions,
+    levels: Vec<Level>,
+}
+
+#[derive(Deserialize)]
+struct Definitions {
+    layers: Vec<LayerDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerDefinition {
+    uid: i32,
+    identifier: String,
+    #[serde(default)]
+    int_grid_values: Vec<IntGridValue>,
+}
+
+#[derive(Deserialize)]
+struct IntGridValue {
+    value: i32,
+    identifier: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Level {
+    identifier: String,
+    px_wid: u32,
+    px_hei: u32,
+    layer_instances: Option<Vec<LayerInstance>>,
+    /// Set when the project saves its levels in separate files
+    external_rel_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__cWid")]
+    c_wid: usize,
+    #[serde(rename = "__cHei")]
+    c_hei: usize,
+    #[serde(rename = "__gridSize")]
+    grid_size: u32,
+    layer_def_uid: i32,
+    #[serde(default)]
+    int_grid_csv: Vec<i32>,
+    #[serde(default)]
+    entity_instances: Vec<EntityInstance>,
+}
+
+#[derive(Deserialize)]
+struct EntityInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__grid")]
+    grid: [i64; 2],
+}
+
+impl Project {
+    /// The tile for every IntGrid value of every layer
+    fn tiles(&self) -> Result<HashMap<(i32, i32), (Tile, Surface)>, LdtkError> {
+        let mut tiles = HashMap::new();
+        for layer in self.defs.layers.iter() {
+            for value in layer.int_grid_values.iter() {
+                let identifier = value.identifier.as_deref().ok_or(LdtkError::UnknownValue {
+                    layer: layer.identifier.clone(),
+                    value: value.value,
+                })?;
+                let tile = parse_identifier(identifier).map_err(|source| LdtkError::Name {
+                    value: value.value,
+                    source,
+                })?;
+                tiles.insert((layer.uid, value.value), tile);
+            }
+        }
+
+        Ok(tiles)
+    }
+}
+
+impl Level {
+    /// Combines all layers of the level, upper layers overwriting lower ones
+    fn to_grid(
+        &self,
+        tiles: &HashMap<(i32, i32), (Tile, Surface)>,
+        grid_size: u32,
+    ) -> Result<Grid<(Tile, Surface)>, LdtkError> {
+        let layers = self
+            .layer_instances
+            .as_ref()
+            .ok_or_else(|| LdtkError::MissingLayers(self.identifier.clone()))?;
+
+        let (w, h) = ((self.px_wid / grid_size) as usize, (self.px_hei / grid_size) as usize);
+        let mut grid = vec![vec![(Tile::Air, Surface::Normal); w]; h];
+
+        // LDtk lists the top layer first
+        for layer in layers.iter().rev() {
+            if !layer.int_grid_csv.is_empty() {
+                if layer.int_grid_csv.len() != layer.c_wid * layer.c_hei {
+                    return Err(LdtkError::LayerSize {
+                        layer: layer.identifier.clone(),
+                        len: layer.int_grid_csv.len(),
+                        expected: layer.c_wid * layer.c_hei,
+                    });
+                }
+
+                for (i, &value) in layer.int_grid_csv.iter().enumerate() {
+                    if value == 0 {
+                        continue;
+                    }
+
+                    let tile = *tiles.get(&(layer.layer_def_uid, value)).ok_or_else(|| {
+                        LdtkError::UnknownValue {
+                            layer: layer.identifier.clone(),
+                            value,
+                        }
+                    })?;
+                    let (x, y) = (i % layer.c_wid, i / layer.c_wid);
+                    if let Some(cell) = grid.get_mut(y).and_then(|row| row.get_mut(x)) {
+                        *cell = tile;
+                    }
+                }
+            }
+
+            for entity in layer.entity_instances.iter() {
+                let Ok(tile) = Tile::from_str(&entity.identifier) else {
+                    warn!("ignoring LDtk entity {:?}", entity.identifier);
+                    continue;
+                };
+
+                // entities may sit on a layer with a different grid size
+                let [x, y] = entity.grid.map(|c| c * layer.grid_size as i64 / grid_size as i64);
+                match grid.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+                    Some(cell) => *cell = (tile, Surface::Normal),
+                    None => warn!("ignoring LDtk entity {:?} outside of the level", entity.identifier),
+                }
+            }
+        }
+
+        Ok(Grid::new(grid))
+    }
+}
+
+#[derive(Default)]
+pub struct LdtkAssetLoader;
+
+/// Settings for [`LdtkAssetLoader`], the tile size is taken from the project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LdtkLoaderSettings {
+    pub anchor: TileAnchor,
+    pub collider_mode: ColliderMode,
+}
+
+/// Reads every level of an LDtk project, including levels saved in separate files,
+/// with their identifiers
+pub(crate) async fn load_ldtk_project(
+    files: &mut impl LevelFiles,
+    path: &Path,
+    bytes: &[u8],
+    settings: &LdtkLoaderSettings,
+) -> Result<Vec<(String, TileGridAsset)>, TileGridAssetLoaderError> {
+    let project: Project = serde_json::from_slice(bytes).map_err(LdtkError::from)?;
+    let tiles = project.tiles()?;
+
+    let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    let mut levels = vec![];
+
+    for level in project.levels {
+        let level = match &level.external_rel_path {
+            Some(path) => {
+                let bytes = files.read(&dir.join(path)).await?;
+                serde_json::from_slice(&bytes).map_err(LdtkError::from)?
+            }
+            None => level,
+        };
+
+        let grid_size = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .find(|l| !l.int_grid_csv.is_empty())
+            .map_or(project.default_grid_size, |l| l.grid_size);
+        let (grid, surfaces) = level.to_grid(&tiles, grid_size)?.unzip();
+        let geometry = TileGeometry {
+            tile_size: grid_size as f32,
+            anchor: settings.anchor,
+        };
+
+        let asset = TileGridAsset::new(grid, surfaces, geometry, settings.collider_mode)?;
+        levels.push((level.identifier, asset));
+    }
+
+    Ok(levels)
+}
+
+impl AssetLoader for LdtkAssetLoader {
+    type Asset = LevelPack;
+    type Settings = LdtkLoaderSettings;
+    type Error = TileGridAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let path = load_context.path().to_path_buf();
+        let mut pack = LevelPack {
+            names: vec![],
+            levels: vec![],
+        };
+
+        for (name, level) in load_ldtk_project(load_context, &path, &bytes, settings).await? {
+            pack.levels.push(load_context.add_labeled_asset(name.clone(), level));
+            pack.names.push(name);
+        }
+
+        Ok(pack)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::tile::{KeyColor, RampOrientation};
+
+    /// IntGrid values 1 to 3 of layer 1 are a solid, an icy solid and a ramp
+    fn tiles() -> HashMap<(i32, i32), (Tile, Surface)> {
+        let defs: Definitions = serde_json::from_str(
+            r#"{ "layers": [{ "uid": 1, "identifier": "Tiles", "intGridValues": [
+                { "value": 1, "identifier": "solid" },
+                { "value": 2, "identifier": "solid_ice" },
+                { "value": 3, "identifier": "ramp_sw" }
+            ] }] }"#,
+        )
+        .unwrap();
+        Project {
+            default_grid_size: 16,
+            defs,
+            levels: vec![],
+        }
+        .tiles()
+        .unwrap()
+    }
+
+    /// A 4 by 2 level of 8 pixel tiles with the given layers, top first
+    fn level(layers: &str) -> Level {
+        serde_json::from_str(&format!(
+            r#"{{ "identifier": "Level_0", "pxWid": 32, "pxHei": 16, "layerInstances": [{layers}] }}"#
+        ))
+        .unwrap()
+    }
+
+    fn int_grid(csv: &str) -> String {
+        format!(
+            r#"{{ "__identifier": "Tiles", "__cWid": 4, "__cHei": 2, "__gridSize": 8, "layerDefUid": 1,
+                "intGridCsv": [{csv}] }}"#
+        )
+    }
+
+    fn entities(entities: &str) -> String {
+        format!(
+            r#"{{ "__identifier": "Entities", "__cWid": 2, "__cHei": 1, "__gridSize": 16, "layerDefUid": 2,
+                "entityInstances": [{entities}] }}"#
+        )
+    }
+
+    #[test]
+    fn identifiers_name_a_tile_and_surface() {
+        assert_eq!(parse_identifier("solid").ok(), Some((Tile::Solid, Surface::Normal)));
+        assert_eq!(parse_identifier("solid_ice").ok(), Some((Tile::Solid, Surface::Ice)));
+        assert_eq!(parse_identifier("solid_no_grapple").ok(), Some((Tile::Solid, Surface::NoGrapple)));
+        assert_eq!(parse_identifier("ramp_sw").ok(), Some((Tile::Ramp(RampOrientation::SW), Surface::Normal)));
+        assert_eq!(
+            parse_identifier("ramp_sw_sticky").ok(),
+            Some((Tile::Ramp(RampOrientation::SW), Surface::Sticky))
+        );
+        assert_eq!(parse_identifier("key_red").ok(), Some((Tile::Key(KeyColor::Red), Surface::Normal)));
+        assert!(parse_identifier("solid_lava").is_err());
+    }
+
+    #[test]
+    fn upper_layers_overwrite_lower_ones() {
+        let level = level(&[int_grid("0, 2, 0, 0, 0, 0, 0, 0"), int_grid("1, 1, 3, 0, 0, 0, 0, 0")].join(","));
+        let grid = level.to_grid(&tiles(), 8).unwrap();
+        assert_eq!(grid, Grid::try_from("XI\\ \n    ").unwrap());
+    }
+
+    #[test]
+    fn entities_are_scaled_to_the_tile_grid() {
+        let entities = entities(
+            r#"{ "__identifier": "Player", "__grid": [0, 0] }, { "__identifier": "Goal", "__grid": [1, 0] }"#,
+        );
+        let level = level(&[entities, int_grid("1, 1, 1, 1, 0, 0, 0, 0")].join(","));
+        let grid = level.to_grid(&tiles(), 8).unwrap();
+        assert_eq!(grid, Grid::try_from("@X0X\n    ").unwrap());
+    }
+
+    #[test]
+    fn layers_have_to_fill_the_level() {
+        let level = level(&int_grid("1, 1, 1"));
+        assert!(matches!(
+            level.to_grid(&tiles(), 8),
+            Err(LdtkError::LayerSize { len: 3, expected: 8, .. })
+        ));
+    }
+
+    #[test]
+    fn values_have_to_be_defined() {
+        let level = level(&int_grid("1, 4, 0, 0, 0, 0, 0, 0"));
+        assert!(matches!(
+            level.to_grid(&tiles(), 8),
+            Err(LdtkError::UnknownValue { value: 4, .. })
+        ));
+
+        let defs: Definitions = serde_json::from_str(
+            r#"{ "layers": [{ "uid": 1, "identifier": "Tiles", "intGridValues": [{ "value": 1 }] }] }"#,
+        )
+        .unwrap();
+        let project = Project {
+            default_grid_size: 16,
+            defs,
+            levels: vec![],
+        };
+        assert!(matches!(project.tiles(), Err(LdtkError::UnknownValue { value: 1, .. })));
+    }
+}
@@ -1,97 +1,273 @@
-﻿use crate::tileset::grid::Grid;
-use crate::tileset::tile::{RampOrientation, Tile, TileImageUnknownPixel};
+﻿use crate::tileset::autotile::Autotile;
+use crate::tileset::bake::{baked_path, level_hash, BakeError, BakedColliders};
+use crate::tileset::collider::{ColliderChunk, ColliderError, ColliderMode, TileCollider, COLLIDER_CHUNK_SIZE};
+use crate::tileset::edit::TileMap;
+use crate::tileset::grid::{Grid, GridRect};
+use crate::tileset::ldtk::{load_ldtk_project, LdtkError};
+use crate::tileset::palette::TilePalette;
+use crate::tileset::render::TileRenderMode;
+use crate::tileset::tile::{KeyColor, Surface, Tile, TileImageUnknownPixels, TileTextUnknownChar};
+use crate::tileset::tiled::{load_tiled_map, TiledError};
 use avian2d::prelude::*;
-use bevy::asset::io::Reader;
-use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::asset::io::{AssetReaderError, Reader};
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, ReadAssetBytesError};
 use bevy::prelude::*;
-use geo::{BooleanOps, Centroid, Coord, CoordsIter, LineString, MultiPolygon, Scale, Translate, TriangulateEarcut, TriangulateSpade, Vector2DOps};
+use geo::{BooleanOps, Coord, LineString, MultiPolygon, Polygon, Scale, Translate, Vector2DOps};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::fs;
 use std::io;
-use geo::triangulate_spade::SpadeTriangulationConfig;
-use itertools::Itertools;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Asset, Debug, Reflect, Clone)]
 pub struct TileGridAsset {
     pub grid: Grid<Tile>,
-    pub collider: Vec<([Vec2; 3], Vec2)>,
+    /// The surface of every tile, same size as `grid`
+    pub surfaces: Grid<Surface>,
+    pub geometry: TileGeometry,
+    /// How `colliders` were built, and how they are rebuilt when a tile is edited
+    pub collider_mode: ColliderMode,
+    pub colliders: Vec<TileCollider>,
 }
 
-impl TileGridAsset {
-    pub fn new(grid: Grid<Tile>, tile_size: f32) -> Self {
-        let polygons = grid_to_polys(&grid, tile_size);
+/// Which point of tile (0, 0) sits on the tilemap's origin
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum TileAnchor {
+    #[default]
+    Center,
+    TopLeft,
+}
 
-        let tris = 
-        polygons.constrained_triangulation(SpadeTriangulationConfig::default()).unwrap()
-            .into_iter()
-            .map(|tri| {
-                let (c_x, c_y) = tri.centroid().0.x_y();
-                let d = Vec2::new(c_x, c_y);
-                let tri = tri.to_array()
-                    .map(|p| Vec2::new(p.x, p.y))
-                    .map(|p| p - d);
+/// The size and placement of the tiles of a [`TileGridAsset`]
+#[derive(Debug, Copy, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct TileGeometry {
+    pub tile_size: f32,
+    pub anchor: TileAnchor,
+}
+
+impl Default for TileGeometry {
+    fn default() -> Self {
+        Self {
+            tile_size: 16.0,
+            anchor: TileAnchor::Center,
+        }
+    }
+}
 
-                (tri, d)
-            })
-            .collect_vec();
+impl TileGeometry {
+    /// The centre of tile (x, y), relative to the tilemap's origin
+    pub fn tile_center(&self, x: usize, y: usize) -> Vec2 {
+        let offset = match self.anchor {
+            TileAnchor::Center => Vec2::ZERO,
+            TileAnchor::TopLeft => Vec2::new(0.5, -0.5),
+        };
+
+        (Vec2::new(x as f32, -(y as f32)) + offset) * self.tile_size
+    }
+
+    /// The tile containing `pos`, which is relative to the tilemap's origin
+    pub fn tile_at(&self, pos: Vec2) -> (i64, i64) {
+        let center = self.tile_center(0, 0);
+        let tile = (pos - center) / self.tile_size;
+
+        (tile.x.round() as i64, -tile.y.round() as i64)
+    }
+
+    /// The area covered by a `w` by `h` grid, relative to the tilemap's origin
+    pub fn bounds(&self, w: usize, h: usize) -> Rect {
+        let corner = Vec2::new(-0.5, 0.5) * self.tile_size;
+        Rect::from_corners(self.tile_center(0, 0) + corner, self.tile_center(w, h) + corner)
+    }
+}
+
+impl TileGridAsset {
+    pub fn new(
+        grid: Grid<Tile>,
+        surfaces: Grid<Surface>,
+        geometry: TileGeometry,
+        collider_mode: ColliderMode,
+    ) -> Result<Self, ColliderError> {
+        let mut asset = Self::with_colliders(grid, surfaces, geometry, collider_mode, vec![]);
+        for (chunk_x, chunk_y) in asset.collider_chunks() {
+            let colliders = asset.chunk_colliders(chunk_x, chunk_y)?;
+            asset.colliders.extend(colliders);
+        }
 
+        Ok(asset)
+    }
+
+    /// Builds the asset from colliders computed ahead of time, e.g. by the `bake-levels` binary
+    pub fn with_colliders(
+        grid: Grid<Tile>,
+        surfaces: Grid<Surface>,
+        geometry: TileGeometry,
+        collider_mode: ColliderMode,
+        colliders: Vec<TileCollider>,
+    ) -> Self {
         Self {
             grid,
-            collider: tris,
+            surfaces,
+            geometry,
+            collider_mode,
+            colliders,
         }
     }
+
+    pub fn bounds(&self) -> Rect {
+        self.geometry.bounds(self.grid.w, self.grid.h)
+    }
+
+    /// The coordinates of every collider chunk, see [`COLLIDER_CHUNK_SIZE`]
+    pub fn collider_chunks(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (w, h) = (self.grid.w.div_ceil(COLLIDER_CHUNK_SIZE), self.grid.h.div_ceil(COLLIDER_CHUNK_SIZE));
+        (0..h).flat_map(move |y| (0..w).map(move |x| (x, y)))
+    }
+
+    /// Builds the colliders of one chunk from the current tiles
+    pub fn chunk_colliders(&self, chunk_x: usize, chunk_y: usize) -> Result<Vec<TileCollider>, ColliderError> {
+        let (x, y) = (chunk_x * COLLIDER_CHUNK_SIZE, chunk_y * COLLIDER_CHUNK_SIZE);
+        let rect = GridRect::new(
+            x,
+            y,
+            COLLIDER_CHUNK_SIZE.min(self.grid.w.saturating_sub(x)),
+            COLLIDER_CHUNK_SIZE.min(self.grid.h.saturating_sub(y)),
+        );
+        let (Some(grid), Some(surfaces)) = (self.grid.sub_grid(rect), self.surfaces.sub_grid(rect)) else {
+            return Ok(vec![]);
+        };
+
+        // the chunk's polygons start at its own tile (0, 0), move them to where it sits in the map
+        let shift = self.geometry.tile_center(x, y) - self.geometry.tile_center(0, 0);
+        let layers = grid_to_polys(&grid, &surfaces, self.geometry)
+            .into_iter()
+            .map(|(surface, polygons)| (surface, polygons.translate(shift.x, shift.y)))
+            .collect();
+
+        self.collider_mode.build(layers, self.geometry, (chunk_x, chunk_y))
+    }
 }
 
 fn removed_in_line(line: &geo::LineString<f32>) -> geo::LineString<f32> {
-    if line.coords_count() < 3 { return line.clone(); }
-    
-    let mut points = line.coords().copied();
-    let (mut a, mut b) = points.next_tuple().unwrap();
-    let mut kept = vec![];
-    
+    // a closed ring of a triangle already has 4 coords, anything less is left alone
+    let coords = line.coords().copied().collect_vec();
+    let [first, second, .., before_last, _] = coords.as_slice() else {
+        return line.clone();
+    };
+
     fn along_path(a: Coord<f32>, b: Coord<f32>, c: Coord<f32>) -> bool {
         let dir_1 = (c - b).try_normalize().unwrap_or_default();
         let dir_2 = (b - a).try_normalize().unwrap_or_default();
         dir_1.dot_product(dir_2) >= 0.9995
     }
-    // maybe add first one
-    {
-        let mut coords = line.coords().copied();
-        let b = coords.next().unwrap();
-        let c = coords.next().unwrap();
-        let _ = coords.next_back().unwrap();  // last coord is a duplicate
-        let a = coords.next_back().unwrap();
+
+    let mut kept = vec![];
+    // maybe add first one, the last coord is a duplicate of it
+    if !along_path(*before_last, *first, *second) {
+        kept.push(*first);
+    }
+
+    for (&a, &b, &c) in coords.iter().tuple_windows() {
         if !along_path(a, b, c) {
             kept.push(b);
         }
     }
-    
-    for p in points {
-        if !along_path(a, b, p) {
-            kept.push(b);
-        }
-        (a, b) = (b, p);
+
+    // a degenerate ring, keep it as is and let the triangulation report it
+    if kept.len() < 3 {
+        return line.clone();
     }
     LineString::<f32>::new(kept)
 }
 
-pub fn grid_to_polys(grid: &Grid<Tile>, tile_size: f32) -> geo::MultiPolygon<f32> {
-    let polygons = grid
-        .iter()
-        .flat_map(|((x, y), t)| {
-            t.to_collider_verts().map(|mut p| {
-                p.translate_mut(
-                    x as f32 * tile_size,
-                    y as f32 * -tile_size,
-                );
-                p.scale_xy_mut(tile_size, tile_size);
+/// Merges the colliders of all tiles into polygons, keeping tiles with different surfaces apart.
+///
+/// Solid tiles are first merged into rectangles (see [`greedy_rectangles`]),
+/// so far fewer polygons need to go through the boolean union.
+pub fn grid_to_polys(
+    grid: &Grid<Tile>,
+    surfaces: &Grid<Surface>,
+    geometry: TileGeometry,
+) -> Vec<(Surface, MultiPolygon<f32>)> {
+    let tile_size = geometry.tile_size;
+    let mut layers: Vec<(Surface, Vec<MultiPolygon<f32>>)> = vec![];
+    let mut push = |surface: Surface, p: Polygon<f32>| {
+        let p = MultiPolygon::new(vec![p]);
+        match layers.iter_mut().find(|(s, _)| *s == surface) {
+            Some((_, polygons)) => polygons.push(p),
+            None => layers.push((surface, vec![p])),
+        }
+    };
+
+    for (surface, [x0, y0, x1, y1]) in greedy_rectangles(grid, surfaces) {
+        let half = Vec2::splat(tile_size / 2.0);
+        let top_left = geometry.tile_center(x0, y0) + Vec2::new(-half.x, half.y);
+        let bottom_right = geometry.tile_center(x1, y1) + Vec2::new(half.x, -half.y);
+        let rect = geo::Rect::new((top_left.x, top_left.y), (bottom_right.x, bottom_right.y));
+        push(surface, rect.to_polygon());
+    }
 
-                p
-            })
-        })
-        .map(|p| MultiPolygon::new(vec![p]))
-        .collect::<Vec<_>>();
+    for ((x, y), t) in grid.iter() {
+        if !matches!(t, Tile::Ramp(_)) {
+            continue;
+        }
+        let Some(mut p) = t.to_collider_verts() else {
+            continue;
+        };
+        let center = geometry.tile_center(x, y);
+        p.translate_mut(center.x, center.y);
+        p.scale_xy_mut(tile_size, tile_size);
+
+        push(surfaces.get(x, y).copied().unwrap_or_default(), p);
+    }
+
+    layers
+        .into_iter()
+        .map(|(surface, polygons)| (surface, union_polys(polygons)))
+        .collect()
+}
+
+/// Covers the solid tiles of each surface with rectangles, given as inclusive `[x0, y0, x1, y1]`.
+/// Each rectangle is grown as far right as possible, then as far down as its whole width allows.
+pub fn greedy_rectangles(grid: &Grid<Tile>, surfaces: &Grid<Surface>) -> Vec<(Surface, [usize; 4])> {
+    let solid = |x: usize, y: usize| match grid.get(x, y) {
+        Some(Tile::Solid) => surfaces.get(x, y).copied(),
+        _ => None,
+    };
+    let mut taken = vec![false; grid.w * grid.h];
+    let mut rects = vec![];
+
+    for y in 0..grid.h {
+        for x in 0..grid.w {
+            let Some(surface) = solid(x, y).filter(|_| !taken[y * grid.w + x]) else {
+                continue;
+            };
+            let free = |x: usize, y: usize, taken: &[bool]| {
+                solid(x, y) == Some(surface) && !taken[y * grid.w + x]
+            };
+
+            let mut x1 = x;
+            while x1 + 1 < grid.w && free(x1 + 1, y, &taken) {
+                x1 += 1;
+            }
+
+            let mut y1 = y;
+            while y1 + 1 < grid.h && (x..=x1).all(|x| free(x, y1 + 1, &taken)) {
+                y1 += 1;
+            }
+
+            for ty in y..=y1 {
+                taken[ty * grid.w + x..=ty * grid.w + x1].fill(true);
+            }
+            rects.push((surface, [x, y, x1, y1]));
+        }
+    }
 
+    rects
+}
+
+pub fn union_polys(polygons: Vec<MultiPolygon<f32>>) -> MultiPolygon<f32> {
     let polys = divide_reduce(polygons, |a, b| a.union(&b))
         .unwrap_or(MultiPolygon::new(vec![]));
     
@@ -106,11 +282,253 @@ pub fn grid_to_polys(grid: &Grid<Tile>, tile_size: f32) -> geo::MultiPolygon<f32
         })
         .collect()
 }
-// fn spawn_polygon(polygon: MultiPolygon)
+
+/// Where the files of a level are read from: the asset server in the asset loaders,
+/// or the file system in the tools (see [`load_level_file`])
+pub(crate) trait LevelFiles {
+    /// Reads the file at `path`, relative to the same root as the level's own path
+    async fn read(&mut self, path: &Path) -> Result<Vec<u8>, TileGridAssetLoaderError>;
+
+    /// Reads a file that may not exist, like a sidecar file
+    async fn read_optional(&mut self, path: &Path) -> Result<Option<Vec<u8>>, TileGridAssetLoaderError>;
+
+    /// Whether baked colliders are used when they are up to date, see [`BakedColliders`]
+    fn use_baked(&self) -> bool {
+        true
+    }
+}
+
+impl LevelFiles for LoadContext<'_> {
+    async fn read(&mut self, path: &Path) -> Result<Vec<u8>, TileGridAssetLoaderError> {
+        Ok(self.read_asset_bytes(path.to_path_buf()).await?)
+    }
+
+    async fn read_optional(&mut self, path: &Path) -> Result<Option<Vec<u8>>, TileGridAssetLoaderError> {
+        match self.read_asset_bytes(path.to_path_buf()).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(ReadAssetBytesError::AssetReaderError(
+                AssetReaderError::NotFound(_) | AssetReaderError::HttpError(404),
+            )) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Reads levels straight from disk, for tools running outside the game
+struct DiskFiles {
+    use_baked: bool,
+}
+
+impl LevelFiles for DiskFiles {
+    async fn read(&mut self, path: &Path) -> Result<Vec<u8>, TileGridAssetLoaderError> {
+        Ok(fs::read(path)?)
+    }
+
+    async fn read_optional(&mut self, path: &Path) -> Result<Option<Vec<u8>>, TileGridAssetLoaderError> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn use_baked(&self) -> bool {
+        self.use_baked
+    }
+}
+
+/// The baked colliders of the level at `path`, if they are there and up to date
+async fn load_baked(files: &mut impl LevelFiles, path: &Path, hash: u64) -> Option<Vec<TileCollider>> {
+    if !files.use_baked() {
+        return None;
+    }
+
+    let path = baked_path(path);
+    let bytes = match files.read_optional(&path).await {
+        Ok(bytes) => bytes?,
+        Err(e) => {
+            warn!("could not read {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    match BakedColliders::from_bytes_matching(&bytes, hash) {
+        Ok(colliders) => Some(colliders),
+        Err(BakeError::Stale | BakeError::Version(_)) => {
+            info!("{} is out of date, generating colliders", path.display());
+            None
+        }
+        Err(e) => {
+            warn!("could not parse {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// A level read from a file, and the hash its baked colliders have to match (see [`level_hash`])
+pub(crate) struct HashedLevel {
+    pub level: TileGridAsset,
+    pub hash: u64,
+}
+
+/// The baked colliders if they are up to date, otherwise generates them
+async fn with_colliders(
+    files: &mut impl LevelFiles,
+    path: &Path,
+    hash: u64,
+    (grid, surfaces): (Grid<Tile>, Grid<Surface>),
+    geometry: TileGeometry,
+    collider_mode: ColliderMode,
+) -> Result<HashedLevel, TileGridAssetLoaderError> {
+    let level = match load_baked(files, path, hash).await {
+        Some(colliders) => TileGridAsset::with_colliders(grid, surfaces, geometry, collider_mode, colliders),
+        None => TileGridAsset::new(grid, surfaces, geometry, collider_mode)?,
+    };
+
+    Ok(HashedLevel { level, hash })
+}
+
+/// Reads a PNG level, using the `.palette.ron` sidecar next to it if there is one
+pub(crate) async fn load_image_level(
+    files: &mut impl LevelFiles,
+    path: &Path,
+    bytes: &[u8],
+    settings: &TileGridLoaderSettings,
+) -> Result<HashedLevel, TileGridAssetLoaderError> {
+    let img = image::load_from_memory(bytes)?;
+
+    let palette_bytes = files.read_optional(&path.with_extension("palette.ron")).await?;
+    let palette = match &palette_bytes {
+        Some(bytes) => ron::de::from_bytes(bytes)?,
+        None => settings.palette.clone(),
+    };
+
+    let tiles = palette.parse_image(&img)?.unzip();
+
+    let hash = level_hash(bytes, palette_bytes.as_deref(), settings);
+    with_colliders(files, path, hash, tiles, settings.geometry, settings.collider_mode).await
+}
+
+/// Reads a level in the plain-text format
+pub(crate) async fn load_text_level(
+    files: &mut impl LevelFiles,
+    path: &Path,
+    bytes: &[u8],
+    settings: &TileGridTextLoaderSettings,
+) -> Result<HashedLevel, TileGridAssetLoaderError> {
+    let text = std::str::from_utf8(bytes)?;
+    let tiles = Grid::<(Tile, Surface)>::try_from(text)?.unzip();
+
+    let hash = level_hash(bytes, None, settings);
+    with_colliders(files, path, hash, tiles, settings.geometry, settings.collider_mode).await
+}
+
+/// The extensions of every level format, see [`load_level_file`]
+pub const LEVEL_EXTENSIONS: &[&str] = &["png", "lvl.txt", "tmj", "tmx", "ldtk"];
+
+/// The extensions of files stored next to levels that aren't levels themselves:
+/// baked colliders, palettes, Tiled tilesets and external LDtk levels
+pub const SIDECAR_EXTENSIONS: &[&str] = &["colliders", "palette.ron", "tsx", "tsj", "ldtkl"];
+
+/// Whether the file name ends in `.extension`
+pub fn has_extension(path: &Path, extension: &str) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(extension))
+        .is_some_and(|name| name.ends_with('.'))
+}
+
+/// A level read by [`load_level_file`]
+pub struct LevelFile {
+    /// The identifier of the level, for files holding several levels like LDtk projects
+    pub name: Option<String>,
+    pub level: TileGridAsset,
+    /// The hash its baked colliders have to match, for the formats that use them (see [`level_hash`])
+    pub hash: Option<u64>,
+}
+
+impl LevelFile {
+    /// The level's file, followed by `#name` for files holding several levels
+    pub fn label(&self, path: &Path) -> String {
+        match &self.name {
+            Some(name) => format!("{}#{name}", path.display()),
+            None => path.display().to_string(),
+        }
+    }
+}
+
+/// The files in `dir` that can hold levels, sorted, without the sidecar files next to them
+pub fn level_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .filter_ok(|path| !SIDECAR_EXTENSIONS.iter().any(|ext| has_extension(path, ext)))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Reads the levels of a file on disk the same way the asset loaders do, with their default settings,
+/// for tools running outside the game. Most formats hold one level, LDtk projects hold several.
+///
+/// With `use_baked`, up to date `.colliders` sidecars are used instead of generating the colliders.
+pub fn load_level_file(path: &Path, use_baked: bool) -> Result<Vec<LevelFile>, TileGridAssetLoaderError> {
+    let files = &mut DiskFiles { use_baked };
+    let bytes = fs::read(path)?;
+    let single = |HashedLevel { level, hash }| {
+        vec![LevelFile {
+            name: None,
+            level,
+            hash: Some(hash),
+        }]
+    };
+
+    bevy::tasks::block_on(async {
+        if has_extension(path, "png") {
+            Ok(single(load_image_level(files, path, &bytes, &default()).await?))
+        } else if has_extension(path, "lvl.txt") {
+            Ok(single(load_text_level(files, path, &bytes, &default()).await?))
+        } else if has_extension(path, "tmj") || has_extension(path, "tmx") {
+            let level = load_tiled_map(files, path, &bytes, &default()).await?;
+            Ok(vec![LevelFile {
+                name: None,
+                level,
+                hash: None,
+            }])
+        } else if has_extension(path, "ldtk") {
+            let levels = load_ldtk_project(files, path, &bytes, &default()).await?;
+            Ok(levels
+                .into_iter()
+                .map(|(name, level)| LevelFile {
+                    name: Some(name),
+                    level,
+                    hash: None,
+                })
+                .collect())
+        } else {
+            Err(TileGridAssetLoaderError::UnsupportedFormat)
+        }
+    })
+}
 
 #[derive(Default)]
 pub struct TileGridAssetLoader;
 
+/// Settings for [`TileGridAssetLoader`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TileGridLoaderSettings {
+    /// The palette used when the level has no `.palette.ron` sidecar file
+    pub palette: TilePalette,
+    pub geometry: TileGeometry,
+    pub collider_mode: ColliderMode,
+}
+
+/// Settings for [`TileGridTextAssetLoader`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TileGridTextLoaderSettings {
+    pub geometry: TileGeometry,
+    pub collider_mode: ColliderMode,
+}
+
 /// Possible errors from loading a TileGridAsset
 #[derive(Debug, Error)]
 pub enum TileGridAssetLoaderError {
@@ -119,37 +537,97 @@ pub enum TileGridAssetLoaderError {
     #[error("Could not load image: {0}")]
     Image(#[from] image::ImageError),
     #[error("Could not parse image: {0}")]
-    Pixel(#[from] TileImageUnknownPixel),
+    Pixel(#[from] TileImageUnknownPixels),
+    #[error("Could not load dependency: {0}")]
+    Dependency(#[from] ReadAssetBytesError),
+    #[error("Could not parse palette: {0}")]
+    Palette(#[from] ron::error::SpannedError),
+    #[error("Could not read text: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("Could not parse text: {0}")]
+    Char(#[from] TileTextUnknownChar),
+    #[error("Could not parse Tiled map: {0}")]
+    Tiled(#[from] TiledError),
+    #[error("Could not parse LDtk project: {0}")]
+    Ldtk(#[from] LdtkError),
+    #[error("Could not build colliders: {0}")]
+    Collider(#[from] ColliderError),
+    #[error("Not a level file, expected one of {}", LEVEL_EXTENSIONS.join(", "))]
+    UnsupportedFormat,
 }
 
 impl AssetLoader for TileGridAssetLoader {
     type Asset = TileGridAsset;
-    type Settings = ();
+    type Settings = TileGridLoaderSettings;
     type Error = TileGridAssetLoaderError;
 
     async fn load<'a>(
         &'a self,
         reader: &'a mut Reader<'_>,
-        _settings: &'a Self::Settings,
-        _load_context: &'a mut LoadContext<'_>,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
-        let img = image::load_from_memory(bytes.as_slice())?;
 
-        let grid = (&img).try_into()?;
+        let path = load_context.path().to_path_buf();
+        Ok(load_image_level(load_context, &path, &bytes, settings).await?.level)
+    }
 
-        // println!("{}", grid);
-        Ok(TileGridAsset::new(grid, 16.0))
+    fn extensions(&self) -> &[&str] {
+        &["png"]
     }
 }
 
+/// Loads levels from the plain-text format (see [`Tile`]'s `TryFrom<char>`)
+#[derive(Default)]
+pub struct TileGridTextAssetLoader;
+
+impl AssetLoader for TileGridTextAssetLoader {
+    type Asset = TileGridAsset;
+    type Settings = TileGridTextLoaderSettings;
+    type Error = TileGridAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let path = load_context.path().to_path_buf();
+        Ok(load_text_level(load_context, &path, &bytes, settings).await?.level)
+    }
 
-#[derive(Component, Default, Clone)]
+    fn extensions(&self) -> &[&str] {
+        &["lvl.txt"]
+    }
+}
+
+
+#[derive(Component, Clone)]
 pub struct TileGridSettings {
     pub solid_texture: Handle<Image>,
     pub ramp_texture: Handle<Image>,
-    pub tile_size: f32,
+    /// Draws solid tiles from an atlas based on their neighbours instead of with `solid_texture`
+    pub autotile: Option<Autotile>,
+    pub render: TileRenderMode,
+    /// The width and height in tiles of each mesh when rendering [`TileRenderMode::Batched`]
+    pub chunk_size: usize,
+}
+
+impl Default for TileGridSettings {
+    fn default() -> Self {
+        Self {
+            solid_texture: default(),
+            ramp_texture: default(),
+            autotile: None,
+            render: default(),
+            chunk_size: 32,
+        }
+    }
 }
 
 #[derive(Bundle, Default)]
@@ -176,9 +654,43 @@ pub struct TileGridBundle {
 #[component(storage = "SparseSet")]
 pub struct TileGridLoadingMarker;
 
+/// Marks the entities spawned from a [`TileGridAsset`], so they can be rebuilt when it changes
+#[derive(Component, Default)]
+pub struct TileGridGenerated;
+
 #[derive(Event)]
 pub struct TileGridLoadEvent(pub TileGridAsset, pub TileGridSettings, pub Entity);
 
+/// Rebuilds live tilemaps when their asset is modified, e.g. by hot-reloading
+pub(crate) fn reload_grid(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<TileGridAsset>>,
+    query: Query<(Entity, &Handle<TileGridAsset>, Option<&Children>), Without<TileGridLoadingMarker>>,
+    generated: Query<(), With<TileGridGenerated>>,
+) {
+    for event in asset_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+
+        for (entity, tile_grid, children) in query.iter() {
+            if tile_grid.id() != *id {
+                continue;
+            }
+
+            info!("reloading tilemap {entity}");
+
+            for &child in children.into_iter().flatten() {
+                if generated.contains(child) {
+                    commands.entity(child).despawn_recursive();
+                }
+            }
+
+            commands.entity(entity).insert(TileGridLoadingMarker);
+        }
+    }
+}
+
 pub(crate) fn spawn_grid(
     mut commands: Commands,
     query: Query<(Entity, &TileGridSettings, &Handle<TileGridAsset>), With<TileGridLoadingMarker>>,
@@ -192,84 +704,74 @@ pub(crate) fn spawn_grid(
 
         load_event.send(TileGridLoadEvent(grid.clone(), settings.clone(), entity));
 
-        // info!("loaded tilemap!");
-
         let mut commands = commands.entity(entity);
 
-        commands.remove::<TileGridLoadingMarker>();
+        commands.remove::<TileGridLoadingMarker>().insert(TileMap::new(grid.clone()));
     }
 }
 
-pub fn spawn_ramps(mut commands: Commands, mut tile_grid: EventReader<TileGridLoadEvent>, mut asset_server: ResMut<AssetServer>) {
-    for TileGridLoadEvent(grid, settings, parent) in tile_grid.read() {
-        let colliders_parent = commands
-            .spawn_empty()
-            .insert(Name::new("Background Tiles"))
-            .insert(SpatialBundle::default())
-            .id();
-        commands.entity(*parent).add_child(colliders_parent);
-        let mut commands = commands.entity(colliders_parent);
-
-        for ((x, y), tile) in grid.grid.iter() {
-            let x = x as f32 * settings.tile_size;
-            let y = y as f32 * settings.tile_size;
+/// Marks the goal sensor of a tilemap
+#[derive(Component)]
+pub struct Goal;
+
+/// A key of a tilemap, picked up by touching it
+#[derive(Component, Debug, Copy, Clone)]
+pub struct Key {
+    pub color: KeyColor,
+    pub map: Entity,
+    pub x: usize,
+    pub y: usize,
+}
 
-            let transform = Transform::from_xyz(x, -y, 0.0);
+/// Marks the sensor of a hazard tile, which kills the player
+#[derive(Component)]
+pub struct Hazard;
+
+/// A checkpoint of a tilemap, which the player respawns at after touching it
+#[derive(Component, Debug, Copy, Clone)]
+pub struct Checkpoint {
+    pub map: Entity,
+    pub x: usize,
+    pub y: usize,
+}
 
-            commands.with_children(|parent| match tile {
-                Tile::Solid => {
-                    parent.spawn((
-                        Name::new("Solid"),
-                        SpriteBundle {
-                            transform,
-                            texture: settings.solid_texture.clone_weak(),
-                            ..default()
-                        },
-                    ));
-                }
-                Tile::Ramp(orientation) => {
-                    let (flip_x, flip_y) = match orientation {
-                        RampOrientation::SW => (false, false),
-                        RampOrientation::SE => (true, false),
-                        RampOrientation::NE => (true, true),
-                        RampOrientation::NW => (false, true),
-                    };
-
-                    parent.spawn((
-                        Name::new(format!("Ramp: {:?}", orientation)),
-                        SpriteBundle {
-                            transform,
-                            texture: settings.ramp_texture.clone_weak(),
-                            sprite: Sprite {
-                                flip_x,
-                                flip_y,
-                                ..default()
-                            },
-                            ..default()
-                        },
-                    ));
-                }
-                Tile::Goal => {
-                    parent.spawn((
-                        Name::new("Goal"),
-                        SpriteBundle {
-                            transform,
-                            texture: asset_server.load("textures/goal.png"),
-                            ..default()
-                        },
-                        Goal,
-                        Collider::rectangle(settings.tile_size, settings.tile_size),
-                        Sensor,
-                    ));
-                }
-                _ => {}
-            });
+impl Checkpoint {
+    pub fn color(active: bool) -> Color {
+        if active {
+            Color::srgb(0.2, 1.0, 1.0)
+        } else {
+            Color::srgb(0.0, 0.35, 0.35)
         }
     }
 }
 
-#[derive(Component)]
-struct Goal;
+/// One tile of a locked door. Doors aren't part of the tilemap's static colliders,
+/// so that opening one only has to despawn its own bodies.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct Door {
+    pub color: KeyColor,
+    pub map: Entity,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Marks colliders the web can't attach to
+#[derive(Component, Default)]
+pub struct NoGrapple;
+
+fn surface_material(surface: Surface) -> (Friction, Restitution) {
+    let friction = Friction::new(surface.friction()).with_combine_rule(match surface {
+        Surface::Ice => CoefficientCombine::Min,
+        Surface::Sticky => CoefficientCombine::Max,
+        _ => CoefficientCombine::Average,
+    });
+    let restitution = Restitution::new(surface.restitution()).with_combine_rule(match surface {
+        Surface::Bouncy => CoefficientCombine::Max,
+        _ => CoefficientCombine::Average,
+    });
+
+    (friction, restitution)
+}
 
 fn divide_reduce<T>(list: Vec<T>, mut reduction: impl FnMut(T, T) -> T) -> Option<T> {
     let mut queue = VecDeque::from(list);
@@ -284,47 +786,47 @@ fn divide_reduce<T>(list: Vec<T>, mut reduction: impl FnMut(T, T) -> T) -> Optio
     queue.pop_back()
 }
 
+/// Spawns the colliders of each chunk under a [`ColliderChunk`] child of the tilemap,
+/// including empty chunks so tiles can be placed in them later
 pub fn spawn_colliders(
     mut commands: Commands,
     mut tile_grid: EventReader<TileGridLoadEvent>,
 ) {
     for TileGridLoadEvent(grid, _, parent) in tile_grid.read() {
-        commands.entity(*parent).with_children(|parent| {
-            // let polys = grid_to_polys(&grid.grid, 16.0);
-            // for p in polys {
-            //     let vertices = p
-            //         .exterior_coords_iter()
-            //         .map(|p| Vec2::new(p.x, p.y))
-            //         .collect::<Vec<_>>();
-            //     parent.spawn((
-            //         Name::new("Polygon Collider (Exterior)"),
-            //         Collider::polyline(vertices, None),
-            //         RigidBody::Static,
-            //         SpatialBundle::default(),
-            //     ));
-            //     for interior in p.interiors() {
-            //         let vertices = interior
-            //             .exterior_coords_iter()
-            //             .map(|p| Vec2::new(p.x, p.y))
-            //             .collect::<Vec<_>>();
-            //         parent.spawn((
-            //             Name::new("Polygon Collider (Interior)"),
-            //             Collider::polyline(vertices, None),
-            //             RigidBody::Static,
-            //             SpatialBundle::default(),
-            //         ));
-            //     }
-            // }
-
-            for ([a, b, c], centroid) in grid.collider.iter().copied() {
-                // Spawn triangle
-                parent.spawn((
-                    Name::new("Triangle"),
-                    Collider::triangle(a, b, c),
-                    SpatialBundle::from_transform(Transform::from_translation(centroid.extend(0.0))),
-                    RigidBody::Static,
-                ));
-            }
-        });
+        let mut chunks = grid.colliders.iter().into_group_map_by(|collider| collider.chunk);
+
+        for (x, y) in grid.collider_chunks() {
+            let colliders = chunks.remove(&(x, y)).unwrap_or_default();
+
+            commands
+                .spawn((
+                    Name::new(format!("Collider Chunk ({x}, {y})")),
+                    SpatialBundle::default(),
+                    TileGridGenerated,
+                    ColliderChunk { map: *parent, x, y },
+                ))
+                .set_parent(*parent)
+                .with_children(|chunk| {
+                    for collider in colliders {
+                        spawn_collider(chunk, collider);
+                    }
+                });
+        }
+    }
+}
+
+pub(crate) fn spawn_collider(parent: &mut ChildBuilder, collider: &TileCollider) {
+    let (friction, restitution) = surface_material(collider.surface);
+
+    let mut entity = parent.spawn((
+        Name::new(collider.shape.name()),
+        collider.shape.to_collider(),
+        SpatialBundle::from_transform(Transform::from_translation(collider.offset.extend(0.0))),
+        RigidBody::Static,
+        friction,
+        restitution,
+    ));
+    if !collider.surface.grappleable() {
+        entity.insert(NoGrapple);
     }
 }
@@ -1,23 +1,46 @@
 use crate::state::InGame;
+use crate::tileset::edit::rebuild_dirty_chunks;
+use crate::tileset::ldtk::{LdtkAssetLoader, LevelPack};
 use crate::tileset::load::*;
+use crate::tileset::render::spawn_tile_chunks;
+use crate::tileset::tiled::TiledAssetLoader;
 use bevy::app::App;
 use bevy::prelude::*;
 
+pub mod autotile;
+pub mod bake;
+pub mod collider;
+pub mod edit;
 pub mod grid;
+pub mod ldtk;
 pub mod load;
+pub mod palette;
+pub mod render;
 pub mod tile;
+pub mod tiled;
+pub mod validate;
 
 pub struct TilePlugin;
 
 impl Plugin for TilePlugin {
     fn build(&self, app: &mut App) {
         app.init_asset::<TileGridAsset>()
+            .init_asset::<LevelPack>()
             .init_asset_loader::<TileGridAssetLoader>()
+            .init_asset_loader::<TileGridTextAssetLoader>()
+            .init_asset_loader::<TiledAssetLoader>()
+            .init_asset_loader::<LdtkAssetLoader>()
             .register_type::<TileGridAsset>()
+            .register_type::<LevelPack>()
             .add_event::<TileGridLoadEvent>()
             .add_systems(
                 Update,
-                (spawn_grid, (spawn_colliders, spawn_ramps))
+                (
+                    reload_grid,
+                    spawn_grid,
+                    (spawn_colliders, spawn_tile_chunks),
+                    rebuild_dirty_chunks,
+                )
                     .chain()
                     .run_if(in_state(InGame)),
             );
@@ -0,0 +1,260 @@
+use crate::tileset::grid::Grid;
+use crate::tileset::tile::{
+    KeyColor, RampOrientation, Surface, Tile, TileImageUnknownPixel, TileImageUnknownPixels, UnknownPixel,
+};
+use bevy::prelude::Reflect;
+use hex_literal::hex;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+/// The colours used by the level PNGs when no palette is given
+pub const DEFAULT_PALETTE: [([u8; 4], Tile, Surface); 21] = [
+    (hex!("ee4080ff"), Tile::Ramp(RampOrientation::NW), Surface::Normal),
+    (hex!("124080ff"), Tile::Ramp(RampOrientation::NE), Surface::Normal),
+    (hex!("28b3cbff"), Tile::Ramp(RampOrientation::SE), Surface::Normal),
+    (hex!("eebf80ff"), Tile::Ramp(RampOrientation::SW), Surface::Normal),
+    (hex!("000000ff"), Tile::Solid, Surface::Normal),
+    (hex!("a0e0ffff"), Tile::Solid, Surface::Ice),
+    (hex!("608020ff"), Tile::Solid, Surface::Sticky),
+    (hex!("ff80ffff"), Tile::Solid, Surface::Bouncy),
+    (hex!("404040ff"), Tile::Solid, Surface::NoGrapple),
+    (hex!("ff0000ff"), Tile::Player, Surface::Normal),
+    (hex!("800080ff"), Tile::Goal, Surface::Normal),
+    (hex!("bfee80ff"), Tile::Key(KeyColor::Plain), Surface::Normal),
+    (hex!("e04040ff"), Tile::Key(KeyColor::Red), Surface::Normal),
+    (hex!("40e040ff"), Tile::Key(KeyColor::Green), Surface::Normal),
+    (hex!("4040e0ff"), Tile::Key(KeyColor::Blue), Surface::Normal),
+    (hex!("806040ff"), Tile::Door(KeyColor::Plain), Surface::Normal),
+    (hex!("801010ff"), Tile::Door(KeyColor::Red), Surface::Normal),
+    (hex!("108010ff"), Tile::Door(KeyColor::Green), Surface::Normal),
+    (hex!("101080ff"), Tile::Door(KeyColor::Blue), Surface::Normal),
+    (hex!("ff8000ff"), Tile::Hazard, Surface::Normal),
+    (hex!("00c0c0ff"), Tile::Checkpoint, Surface::Normal),
+];
+
+/// Maps pixel colours of a level image to [`Tile`]s and their [`Surface`].
+///
+/// Can be given through the loader settings or as a RON sidecar file next to the level,
+/// e.g. `levels/main-level.palette.ron` for `levels/main-level.png`:
+/// ```ron
+/// (
+///     colors: [
+///         ("000000ff", Solid),
+///         ("a0e0ffff", Solid, Ice),
+///         ("ee4080ff", Ramp(NW)),
+///     ],
+///     tolerance: 4,
+/// )
+/// ```
+/// Fully transparent pixels are always [`Tile::Air`].
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct TilePalette {
+    pub colors: Vec<PaletteEntry>,
+    /// The maximum difference per channel for a pixel to still match a colour
+    #[serde(default)]
+    pub tolerance: u8,
+}
+
+/// A colour, its tile and optionally its surface (which defaults to [`Surface::Normal`])
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct PaletteEntry(pub PaletteColor, pub Tile, #[serde(default)] pub Surface);
+
+impl Default for TilePalette {
+    fn default() -> Self {
+        Self {
+            colors: DEFAULT_PALETTE
+                .iter()
+                .map(|&(color, tile, surface)| PaletteEntry(PaletteColor(color), tile, surface))
+                .collect(),
+            tolerance: 0,
+        }
+    }
+}
+
+impl TilePalette {
+    /// Finds the tile for this pixel, picking the closest colour if several are within tolerance.
+    pub fn get(&self, pixel: Rgba<u8>) -> Result<(Tile, Surface), UnknownPixel> {
+        if pixel.0[3] == 0 {
+            return Ok((Tile::Air, Surface::Normal));
+        }
+
+        self.colors
+            .iter()
+            .filter(|PaletteEntry(color, ..)| color.max_difference(pixel) <= self.tolerance)
+            .min_by_key(|PaletteEntry(color, ..)| color.distance_squared(pixel))
+            .map(|&PaletteEntry(_, tile, surface)| (tile, surface))
+            .ok_or(UnknownPixel(pixel))
+    }
+
+    /// The closest colour in the palette, ignoring the tolerance
+    pub fn nearest(&self, pixel: Rgba<u8>) -> Option<(PaletteColor, Tile)> {
+        self.colors
+            .iter()
+            .min_by_key(|PaletteEntry(color, ..)| color.distance_squared(pixel))
+            .map(|&PaletteEntry(color, tile, _)| (color, tile))
+    }
+
+    pub fn parse_image(
+        &self,
+        image: &DynamicImage,
+    ) -> Result<Grid<(Tile, Surface)>, TileImageUnknownPixels> {
+        Grid::try_from_iter_all(
+            (0..image.height())
+                .map(move |y| (0..image.width()).map(move |x| self.get(image.get_pixel(x, y)))),
+        )
+        .map_err(|errors| {
+            let pixels = errors
+                .into_iter()
+                .map(|(x, y, e)| TileImageUnknownPixel::new(x as u32, y as u32, e.0))
+                .collect();
+            TileImageUnknownPixels::new(pixels, self)
+        })
+    }
+
+    /// The first colour of exactly this tile and surface
+    pub fn color_of(&self, tile: Tile, surface: Surface) -> Option<PaletteColor> {
+        self.colors
+            .iter()
+            .find(|&&PaletteEntry(_, t, s)| t == tile && s == surface)
+            .map(|&PaletteEntry(color, ..)| color)
+    }
+
+    /// Draws the grid as a level image, parseable again with [`TilePalette::parse_image`].
+    /// Air is written as a fully transparent pixel.
+    pub fn encode_image(&self, grid: &Grid<(Tile, Surface)>) -> Result<RgbaImage, UnencodableTile> {
+        let mut image = RgbaImage::new(grid.w as u32, grid.h as u32);
+        for ((x, y), &(tile, surface)) in grid.iter() {
+            let pixel = match (tile, surface) {
+                (Tile::Air, Surface::Normal) => [0; 4],
+                _ => self
+                    .color_of(tile, surface)
+                    .ok_or(UnencodableTile(tile, surface))?
+                    .0,
+            };
+            image.put_pixel(x as u32, y as u32, Rgba(pixel));
+        }
+
+        Ok(image)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Error)]
+#[error("The palette has no colour for {0:?} with surface {1:?}")]
+pub struct UnencodableTile(pub Tile, pub Surface);
+
+impl Grid<(Tile, Surface)> {
+    /// Draws the grid with the [`DEFAULT_PALETTE`], which only has surfaces for solid tiles
+    pub fn to_image(&self) -> Result<RgbaImage, UnencodableTile> {
+        TilePalette::default().encode_image(self)
+    }
+}
+
+impl Grid<Tile> {
+    /// Draws the grid with the [`DEFAULT_PALETTE`]
+    pub fn to_image(&self) -> RgbaImage {
+        self.clone()
+            .map(|tile| (tile, Surface::Normal))
+            .to_image()
+            .expect("the default palette covers every tile")
+    }
+}
+
+/// An RGBA colour, written as a hex string (`"rrggbbaa"`) in palette files
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+pub struct PaletteColor(pub [u8; 4]);
+
+impl PaletteColor {
+    pub fn max_difference(self, pixel: Rgba<u8>) -> u8 {
+        self.0
+            .iter()
+            .zip(pixel.0)
+            .map(|(&a, b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn distance_squared(self, pixel: Rgba<u8>) -> u32 {
+        self.0
+            .iter()
+            .zip(pixel.0)
+            .map(|(&a, b)| (a.abs_diff(b) as u32).pow(2))
+            .sum()
+    }
+}
+
+impl Display for PaletteColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b, a] = self.0;
+        write!(f, "{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+impl Serialize for PaletteColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PaletteColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let hex = hex.trim_start_matches('#');
+        let channel = |i: usize| {
+            hex.get(i * 2..i * 2 + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+
+        match (hex.len(), channel(0), channel(1), channel(2), channel(3)) {
+            (8, Some(r), Some(g), Some(b), Some(a)) => Ok(Self([r, g, b, a])),
+            (6, Some(r), Some(g), Some(b), None) => Ok(Self([r, g, b, 255])),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid colour {hex:?}, expected \"rrggbb\" or \"rrggbbaa\""
+            ))),
+        }
+    }
+}
+
+/// Every entry of the [`DEFAULT_PALETTE`] and air, two to a row
+#[cfg(test)]
+pub(crate) fn every_entry() -> Grid<(Tile, Surface)> {
+    let mut cells: Vec<_> = DEFAULT_PALETTE.iter().map(|&(_, tile, surface)| (tile, surface)).collect();
+    cells.push((Tile::Air, Surface::Normal));
+    Grid::from_vec(2, cells.len() / 2, cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_round_trips_every_entry() {
+        let grid = every_entry();
+        let image = DynamicImage::ImageRgba8(grid.to_image().unwrap());
+        assert_eq!(TilePalette::default().parse_image(&image).unwrap(), grid);
+    }
+
+    #[test]
+    fn tiles_round_trip_through_image() {
+        let grid = every_entry().unzip().0;
+        let image = DynamicImage::ImageRgba8(grid.to_image());
+        assert_eq!(Grid::<Tile>::try_from(&image).unwrap(), grid);
+    }
+
+    #[test]
+    fn missing_surface_is_unencodable() {
+        let grid = Grid::new(vec![vec![(Tile::Ramp(RampOrientation::NW), Surface::Ice)]]);
+        assert!(matches!(
+            grid.to_image(),
+            Err(UnencodableTile(Tile::Ramp(RampOrientation::NW), Surface::Ice))
+        ));
+    }
+
+    #[test]
+    fn color_of_needs_the_exact_surface() {
+        let palette = TilePalette::default();
+        assert_eq!(palette.color_of(Tile::Solid, Surface::Ice), Some(PaletteColor(hex!("a0e0ffff"))));
+        assert_eq!(palette.color_of(Tile::Goal, Surface::Ice), None);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::tileset::load::{Checkpoint, Door, Goal, Hazard, Key, TileGridAsset, TileGridGenerated, TileGridLoadEvent, TileGridSettings};
+use crate::tileset::tile::{RampOrientation, Tile};
+use avian2d::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use std::ops::Range;
+
+/// How the static tiles of a tilemap are drawn
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Reflect)]
+pub enum TileRenderMode {
+    /// One mesh per texture per chunk of tiles
+    #[default]
+    Batched,
+    /// One `Name`d sprite entity per tile, easier to inspect while debugging
+    PerTile,
+}
+
+/// The quads of one texture in a chunk
+#[derive(Default)]
+struct ChunkMesh {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl ChunkMesh {
+    /// Adds a tile-sized quad, `uv` being the (min, max) of its texture region
+    fn push(&mut self, center: Vec2, size: f32, (min, max): (Vec2, Vec2), flip_x: bool, flip_y: bool, color: Color) {
+        let (u0, u1) = if flip_x { (max.x, min.x) } else { (min.x, max.x) };
+        let (v0, v1) = if flip_y { (max.y, min.y) } else { (min.y, max.y) };
+        let half = size / 2.0;
+        let start = self.positions.len() as u32;
+
+        // top-left, top-right, bottom-right, bottom-left; uv y points down
+        self.positions.extend([
+            [center.x - half, center.y + half, 0.0],
+            [center.x + half, center.y + half, 0.0],
+            [center.x + half, center.y - half, 0.0],
+            [center.x - half, center.y - half, 0.0],
+        ]);
+        self.uvs.extend([[u0, v0], [u1, v0], [u1, v1], [u0, v1]]);
+        self.colors.extend([color.to_linear().to_f32_array(); 4]);
+        self.indices
+            .extend([start, start + 2, start + 1, start, start + 3, start + 2]);
+    }
+
+    fn into_mesh(self) -> Option<Mesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+
+        Some(
+            Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+                .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+                .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+                .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+                .with_inserted_indices(Indices::U32(self.indices)),
+        )
+    }
+}
+
+/// One chunk of a spawned tilemap, `chunk_size` tiles wide and high.
+/// Its children are the meshes or sprites of its tiles, and its goals, keys, doors, hazards and checkpoints.
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TileChunk {
+    pub map: Entity,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// The materials of the chunk meshes of a tilemap, shared by all its chunks
+#[derive(Component, Debug, Clone)]
+pub struct ChunkMaterials {
+    solid: Handle<ColorMaterial>,
+    ramp: Handle<ColorMaterial>,
+}
+
+/// Spawns and rebuilds the contents of [`TileChunk`]s
+#[derive(SystemParam)]
+pub(crate) struct TileChunkBuilder<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    layouts: Res<'w, Assets<TextureAtlasLayout>>,
+    asset_server: Res<'w, AssetServer>,
+}
+
+impl TileChunkBuilder<'_, '_> {
+    /// Spawns every chunk of `grid` under a "Tile Chunks" child of `map`,
+    /// including empty ones so tiles can be placed in them later
+    fn spawn_chunks(&mut self, map: Entity, grid: &TileGridAsset, settings: &TileGridSettings) {
+        let solid_texture = match &settings.autotile {
+            Some(autotile) => autotile.texture.clone(),
+            None => settings.solid_texture.clone(),
+        };
+        let materials = ChunkMaterials {
+            solid: self.materials.add(ColorMaterial::from(solid_texture)),
+            ramp: self.materials.add(ColorMaterial::from(settings.ramp_texture.clone())),
+        };
+
+        let chunk_size = settings.chunk_size.max(1);
+        let chunks = self
+            .commands
+            .spawn((
+                Name::new("Tile Chunks"),
+                SpatialBundle::default(),
+                TileGridGenerated,
+            ))
+            .set_parent(map)
+            .id();
+
+        for y in 0..grid.grid.h.div_ceil(chunk_size) {
+            for x in 0..grid.grid.w.div_ceil(chunk_size) {
+                let chunk = TileChunk { map, x, y };
+                let entity = self
+                    .commands
+                    .spawn((
+                        Name::new(format!("Tile Chunk ({x}, {y})")),
+                        SpatialBundle::default(),
+                        chunk,
+                        materials.clone(),
+                    ))
+                    .set_parent(chunks)
+                    .id();
+
+                self.fill(entity, chunk, &materials, grid, settings);
+            }
+        }
+    }
+
+    /// Spawns the tiles of `chunk` as children of its `entity`: one mesh per texture when
+    /// [`TileRenderMode::Batched`], or one sprite per tile when [`TileRenderMode::PerTile`].
+    pub fn fill(
+        &mut self,
+        entity: Entity,
+        chunk: TileChunk,
+        materials: &ChunkMaterials,
+        grid: &TileGridAsset,
+        settings: &TileGridSettings,
+    ) {
+        let chunk_size = settings.chunk_size.max(1);
+        let (x0, y0) = (chunk.x * chunk_size, chunk.y * chunk_size);
+        let xs = x0..(x0 + chunk_size).min(grid.grid.w);
+        let ys = y0..(y0 + chunk_size).min(grid.grid.h);
+
+        if settings.render == TileRenderMode::Batched {
+            let (solid, ramps) = build_chunk(grid, settings, &self.layouts, xs.clone(), ys.clone());
+
+            for (mesh, material, name) in [(solid, &materials.solid, "Solid Tiles"), (ramps, &materials.ramp, "Ramp Tiles")] {
+                let Some(mesh) = mesh.into_mesh() else {
+                    continue;
+                };
+
+                self.commands
+                    .spawn((
+                        Name::new(name),
+                        MaterialMesh2dBundle {
+                            mesh: Mesh2dHandle(self.meshes.add(mesh)),
+                            material: material.clone(),
+                            ..default()
+                        },
+                    ))
+                    .set_parent(entity);
+            }
+        }
+
+        for y in ys {
+            for x in xs.clone() {
+                self.spawn_tile(entity, chunk.map, grid, settings, x, y);
+            }
+        }
+    }
+
+    /// Spawns the entity of a single tile, if it needs one: goals, keys, doors, hazards and checkpoints always do,
+    /// solid tiles and ramps only when [`TileRenderMode::PerTile`]
+    fn spawn_tile(
+        &mut self,
+        parent: Entity,
+        map: Entity,
+        grid: &TileGridAsset,
+        settings: &TileGridSettings,
+        x: usize,
+        y: usize,
+    ) {
+        let tile_size = grid.geometry.tile_size;
+        let custom_size = Some(Vec2::splat(tile_size));
+        let per_tile = settings.render == TileRenderMode::PerTile;
+
+        let transform = Transform::from_translation(grid.geometry.tile_center(x, y).extend(0.0));
+        let color = grid.surfaces.get(x, y).copied().unwrap_or_default().color();
+
+        let tile = match grid.grid.get(x, y) {
+            Some(&Tile::Solid) if per_tile => {
+                let (texture, atlas) = match &settings.autotile {
+                    Some(autotile) => (
+                        autotile.texture.clone_weak(),
+                        Some(TextureAtlas {
+                            layout: autotile.layout.clone_weak(),
+                            index: autotile.rules.index(&grid.grid, x, y),
+                        }),
+                    ),
+                    None => (settings.solid_texture.clone_weak(), None),
+                };
+
+                let mut solid = self.commands.spawn((
+                    Name::new("Solid"),
+                    SpriteBundle {
+                        transform,
+                        texture,
+                        sprite: Sprite {
+                            color,
+                            custom_size,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ));
+                if let Some(atlas) = atlas {
+                    solid.insert(atlas);
+                }
+                solid.id()
+            }
+            Some(&Tile::Ramp(orientation)) if per_tile => {
+                let (flip_x, flip_y) = ramp_flip(orientation);
+
+                self.commands
+                    .spawn((
+                        Name::new(format!("Ramp: {:?}", orientation)),
+                        SpriteBundle {
+                            transform,
+                            texture: settings.ramp_texture.clone_weak(),
+                            sprite: Sprite {
+                                color,
+                                flip_x,
+                                flip_y,
+                                custom_size,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ))
+                    .id()
+            }
+            Some(Tile::Goal) => self
+                .commands
+                .spawn((
+                    Name::new("Goal"),
+                    SpriteBundle {
+                        transform,
+                        texture: self.asset_server.load("textures/goal.png"),
+                        sprite: Sprite {
+                            custom_size,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Goal,
+                    Collider::rectangle(tile_size, tile_size),
+                    Sensor,
+                ))
+                .id(),
+            Some(&Tile::Key(color)) => self
+                .commands
+                .spawn((
+                    Name::new(format!("Key: {:?}", color)),
+                    SpriteBundle {
+                        transform,
+                        sprite: Sprite {
+                            color: color.color(),
+                            custom_size: Some(Vec2::splat(tile_size * 0.4)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Key { color, map, x, y },
+                    Collider::rectangle(tile_size * 0.4, tile_size * 0.4),
+                    Sensor,
+                ))
+                .id(),
+            Some(&Tile::Door(color)) => self
+                .commands
+                .spawn((
+                    Name::new(format!("Door: {:?}", color)),
+                    SpriteBundle {
+                        transform,
+                        texture: settings.solid_texture.clone_weak(),
+                        sprite: Sprite {
+                            color: color.color(),
+                            custom_size,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Door { color, map, x, y },
+                    RigidBody::Static,
+                    Collider::rectangle(tile_size, tile_size),
+                ))
+                .id(),
+            Some(Tile::Hazard) => self
+                .commands
+                .spawn((
+                    Name::new("Hazard"),
+                    SpriteBundle {
+                        transform,
+                        sprite: Sprite {
+                            color: Color::srgb(1.0, 0.5, 0.0),
+                            custom_size: Some(Vec2::splat(tile_size * 0.8)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Hazard,
+                    // a little smaller than the tile, so brushing past a corner is survivable
+                    Collider::rectangle(tile_size * 0.8, tile_size * 0.8),
+                    Sensor,
+                ))
+                .id(),
+            Some(Tile::Checkpoint) => self
+                .commands
+                .spawn((
+                    Name::new("Checkpoint"),
+                    SpriteBundle {
+                        transform,
+                        sprite: Sprite {
+                            // recoloured once activated, see `crate::checkpoint`
+                            color: Checkpoint::color(false),
+                            custom_size: Some(Vec2::new(tile_size * 0.3, tile_size)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Checkpoint { map, x, y },
+                    Collider::rectangle(tile_size, tile_size),
+                    Sensor,
+                ))
+                .id(),
+            _ => return,
+        };
+
+        self.commands.entity(tile).set_parent(parent);
+    }
+}
+
+/// Spawns the chunks of each newly spawned tilemap, see [`TileChunk`]
+pub(crate) fn spawn_tile_chunks(mut builder: TileChunkBuilder, mut tile_grid: EventReader<TileGridLoadEvent>) {
+    for TileGridLoadEvent(grid, settings, parent) in tile_grid.read() {
+        builder.spawn_chunks(*parent, grid, settings);
+    }
+}
+
+fn build_chunk(
+    grid: &TileGridAsset,
+    settings: &TileGridSettings,
+    layouts: &Assets<TextureAtlasLayout>,
+    xs: Range<usize>,
+    ys: Range<usize>,
+) -> (ChunkMesh, ChunkMesh) {
+    let (mut solid, mut ramps) = (ChunkMesh::default(), ChunkMesh::default());
+    let full = (Vec2::ZERO, Vec2::ONE);
+    let size = grid.geometry.tile_size;
+
+    for y in ys {
+        for x in xs.clone() {
+            let center = grid.geometry.tile_center(x, y);
+            let color = grid.surfaces.get(x, y).copied().unwrap_or_default().color();
+
+            match grid.grid.get(x, y) {
+                Some(Tile::Solid) => {
+                    let uv = settings
+                        .autotile
+                        .as_ref()
+                        .and_then(|autotile| {
+                            let layout = layouts.get(&autotile.layout)?;
+                            let rect = layout.textures.get(autotile.rules.index(&grid.grid, x, y))?;
+                            let atlas_size = layout.size.as_vec2();
+                            Some((rect.min.as_vec2() / atlas_size, rect.max.as_vec2() / atlas_size))
+                        })
+                        .unwrap_or(full);
+
+                    solid.push(center, size, uv, false, false, color);
+                }
+                Some(&Tile::Ramp(orientation)) => {
+                    let (flip_x, flip_y) = ramp_flip(orientation);
+                    ramps.push(center, size, full, flip_x, flip_y, color);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    (solid, ramps)
+}
+
+/// How `ramp_texture`, which shows a [`RampOrientation::SW`] ramp, is flipped for each orientation
+pub fn ramp_flip(orientation: RampOrientation) -> (bool, bool) {
+    match orientation {
+        RampOrientation::SW => (false, false),
+        RampOrientation::SE => (true, false),
+        RampOrientation::NE => (true, true),
+        RampOrientation::NW => (false, true),
+    }
+}
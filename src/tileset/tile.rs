@@ -1,18 +1,27 @@
-use crate::tileset::grid::Grid;
-use bevy::prelude::{Reflect, Vec2};
+use crate::tileset::grid::{Connectivity, Grid};
+use crate::tileset::palette::{PaletteColor, TilePalette, DEFAULT_PALETTE};
+use bevy::prelude::{Color, Reflect, Vec2};
 use geo::{LineString, Polygon};
-use hex_literal::hex;
-use image::{DynamicImage, GenericImageView, Rgba};
+use itertools::Itertools;
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use thiserror::Error;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Reflect)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Reflect, Serialize, Deserialize)]
 pub enum Tile {
     Solid,
     Air,
     Player,
     Goal,
-    Key,
+    Key(KeyColor),
+    /// Blocks the player and web until opened with a matching key
+    Door(KeyColor),
+    /// Kills the player on touch
+    Hazard,
+    /// Where the player respawns after touching it
+    Checkpoint,
     Ramp(RampOrientation),
 }
 
@@ -39,7 +48,34 @@ impl Tile {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Reflect)]
+/// The colour of a key and the doors it opens
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Reflect, Serialize, Deserialize)]
+pub enum KeyColor {
+    /// Plain doors open with a key of any colour
+    #[default]
+    Plain,
+    Red,
+    Green,
+    Blue,
+}
+
+impl KeyColor {
+    /// Whether a key of this colour opens a door of colour `door`
+    pub fn opens(self, door: KeyColor) -> bool {
+        door == KeyColor::Plain || self == door
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            KeyColor::Plain => Color::srgb(1.0, 0.85, 0.2),
+            KeyColor::Red => Color::srgb(0.9, 0.2, 0.2),
+            KeyColor::Green => Color::srgb(0.2, 0.8, 0.2),
+            KeyColor::Blue => Color::srgb(0.25, 0.35, 0.95),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Reflect, Serialize, Deserialize)]
 pub enum RampOrientation {
     SW,
     SE,
@@ -57,6 +93,78 @@ impl RampOrientation {
         }
         .map(|x| x - Vec2::splat(0.5))
     }
+
+    /// Mirrors the ramp left to right
+    pub fn flip_x(self) -> Self {
+        match self {
+            RampOrientation::SW => RampOrientation::SE,
+            RampOrientation::SE => RampOrientation::SW,
+            RampOrientation::NE => RampOrientation::NW,
+            RampOrientation::NW => RampOrientation::NE,
+        }
+    }
+
+    /// Mirrors the ramp top to bottom
+    pub fn flip_y(self) -> Self {
+        match self {
+            RampOrientation::SW => RampOrientation::NW,
+            RampOrientation::SE => RampOrientation::NE,
+            RampOrientation::NE => RampOrientation::SE,
+            RampOrientation::NW => RampOrientation::SW,
+        }
+    }
+
+    /// Mirrors the ramp along the diagonal from its top-left to bottom-right corner
+    pub fn transpose(self) -> Self {
+        match self {
+            RampOrientation::SW => RampOrientation::NE,
+            RampOrientation::NE => RampOrientation::SW,
+            o => o,
+        }
+    }
+}
+
+/// The material of a tile, stored in its own layer next to the [`Tile`]s
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Surface {
+    #[default]
+    Normal,
+    Ice,
+    Sticky,
+    Bouncy,
+    /// The web can't attach to this surface
+    NoGrapple,
+}
+
+impl Surface {
+    pub fn friction(self) -> f32 {
+        match self {
+            Surface::Ice => 0.0,
+            Surface::Sticky => 1.0,
+            _ => 0.3,
+        }
+    }
+
+    pub fn restitution(self) -> f32 {
+        match self {
+            Surface::Bouncy => 0.8,
+            _ => 0.0,
+        }
+    }
+
+    pub fn grappleable(self) -> bool {
+        self != Surface::NoGrapple
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Surface::Normal => Color::WHITE,
+            Surface::Ice => Color::srgb(0.7, 0.9, 1.0),
+            Surface::Sticky => Color::srgb(0.6, 0.8, 0.3),
+            Surface::Bouncy => Color::srgb(1.0, 0.6, 1.0),
+            Surface::NoGrapple => Color::srgb(0.5, 0.5, 0.5),
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -67,26 +175,13 @@ impl TryFrom<Rgba<u8>> for Tile {
     type Error = UnknownPixel;
 
     fn try_from(value: Rgba<u8>) -> Result<Self, Self::Error> {
-        const NW: [u8; 4] = hex!("ee4080ff");
-        const NE: [u8; 4] = hex!("124080ff");
-        const SE: [u8; 4] = hex!("28b3cbff");
-        const SW: [u8; 4] = hex!("eebf80ff");
-        const PLAYER: [u8; 4] = [255, 0, 0, 255];
-        const SOLID: [u8; 4] = hex!("000000ff");
-        const GOAL: [u8; 4] = hex!("800080ff");
-        const KEY: [u8; 4] = hex!("bfee80ff");
-
         match value {
-            Rgba(NW) => Ok(Tile::Ramp(RampOrientation::NW)),
-            Rgba(NE) => Ok(Tile::Ramp(RampOrientation::NE)),
-            Rgba(SE) => Ok(Tile::Ramp(RampOrientation::SE)),
-            Rgba(SW) => Ok(Tile::Ramp(RampOrientation::SW)),
-            Rgba(SOLID) => Ok(Tile::Solid),
-            Rgba(PLAYER) => Ok(Tile::Player),
-            Rgba(GOAL) => Ok(Tile::Goal),
-            Rgba(KEY) => Ok(Tile::Key),
             Rgba([_, _, _, 0]) => Ok(Tile::Air),
-            _ => Err(UnknownPixel(value)),
+            Rgba(color) => DEFAULT_PALETTE
+                .iter()
+                .find(|(c, _, _)| *c == color)
+                .map(|&(_, tile, _)| tile)
+                .ok_or(UnknownPixel(value)),
         }
     }
 }
@@ -102,11 +197,119 @@ impl TryFrom<char> for Tile {
             '/' => Ok(Self::Ramp(RampOrientation::SE)),
             '`' => Ok(Self::Ramp(RampOrientation::NE)),
             ',' => Ok(Self::Ramp(RampOrientation::NW)),
+            '@' => Ok(Self::Player),
+            '0' => Ok(Self::Goal),
+            '$' => Ok(Self::Key(KeyColor::Plain)),
+            'r' => Ok(Self::Key(KeyColor::Red)),
+            'g' => Ok(Self::Key(KeyColor::Green)),
+            'b' => Ok(Self::Key(KeyColor::Blue)),
+            'D' => Ok(Self::Door(KeyColor::Plain)),
+            'R' => Ok(Self::Door(KeyColor::Red)),
+            'G' => Ok(Self::Door(KeyColor::Green)),
+            'B' => Ok(Self::Door(KeyColor::Blue)),
+            '^' => Ok(Self::Hazard),
+            'C' => Ok(Self::Checkpoint),
             c => Err(c),
         }
     }
 }
 
+/// The character of a tile in the text format, the inverse of `Tile::try_from(char)`
+impl From<Tile> for char {
+    fn from(value: Tile) -> Self {
+        match value {
+            Tile::Solid => 'X',
+            Tile::Air => ' ',
+            Tile::Ramp(RampOrientation::SW) => '\\',
+            Tile::Ramp(RampOrientation::SE) => '/',
+            Tile::Ramp(RampOrientation::NE) => '`',
+            Tile::Ramp(RampOrientation::NW) => ',',
+            Tile::Player => '@',
+            Tile::Goal => '0',
+            Tile::Key(KeyColor::Plain) => '$',
+            Tile::Key(KeyColor::Red) => 'r',
+            Tile::Key(KeyColor::Green) => 'g',
+            Tile::Key(KeyColor::Blue) => 'b',
+            Tile::Door(KeyColor::Plain) => 'D',
+            Tile::Door(KeyColor::Red) => 'R',
+            Tile::Door(KeyColor::Green) => 'G',
+            Tile::Door(KeyColor::Blue) => 'B',
+            Tile::Hazard => '^',
+            Tile::Checkpoint => 'C',
+        }
+    }
+}
+
+/// The inverse of [`parse_tile_char`]. Only solid tiles can carry a surface in the text format.
+pub fn tile_char(tile: Tile, surface: Surface) -> char {
+    match (tile, surface) {
+        (Tile::Solid, Surface::Ice) => 'I',
+        (Tile::Solid, Surface::Sticky) => 'S',
+        (Tile::Solid, Surface::Bouncy) => 'O',
+        (Tile::Solid, Surface::NoGrapple) => 'N',
+        (tile, _) => tile.into(),
+    }
+}
+
+/// Parses a character of the text format, which can also give solid tiles a [`Surface`]
+pub fn parse_tile_char(value: char) -> Result<(Tile, Surface), char> {
+    match value {
+        'I' => Ok((Tile::Solid, Surface::Ice)),
+        'S' => Ok((Tile::Solid, Surface::Sticky)),
+        'O' => Ok((Tile::Solid, Surface::Bouncy)),
+        'N' => Ok((Tile::Solid, Surface::NoGrapple)),
+        c => Tile::try_from(c).map(|tile| (tile, Surface::Normal)),
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+#[error("Unknown name {0:?}")]
+pub struct UnknownName(pub String);
+
+/// Parses tile names used by external editors, e.g. `solid` or `ramp_sw`
+impl FromStr for Tile {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "solid" => Ok(Tile::Solid),
+            "air" => Ok(Tile::Air),
+            "player" => Ok(Tile::Player),
+            "goal" => Ok(Tile::Goal),
+            "key" => Ok(Tile::Key(KeyColor::Plain)),
+            "key_red" => Ok(Tile::Key(KeyColor::Red)),
+            "key_green" => Ok(Tile::Key(KeyColor::Green)),
+            "key_blue" => Ok(Tile::Key(KeyColor::Blue)),
+            "door" => Ok(Tile::Door(KeyColor::Plain)),
+            "door_red" => Ok(Tile::Door(KeyColor::Red)),
+            "door_green" => Ok(Tile::Door(KeyColor::Green)),
+            "door_blue" => Ok(Tile::Door(KeyColor::Blue)),
+            "hazard" | "spike" => Ok(Tile::Hazard),
+            "checkpoint" => Ok(Tile::Checkpoint),
+            "ramp_sw" => Ok(Tile::Ramp(RampOrientation::SW)),
+            "ramp_se" => Ok(Tile::Ramp(RampOrientation::SE)),
+            "ramp_ne" => Ok(Tile::Ramp(RampOrientation::NE)),
+            "ramp_nw" => Ok(Tile::Ramp(RampOrientation::NW)),
+            _ => Err(UnknownName(s.to_string())),
+        }
+    }
+}
+
+impl FromStr for Surface {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(Surface::Normal),
+            "ice" => Ok(Surface::Ice),
+            "sticky" => Ok(Surface::Sticky),
+            "bouncy" => Ok(Surface::Bouncy),
+            "no_grapple" => Ok(Surface::NoGrapple),
+            _ => Err(UnknownName(s.to_string())),
+        }
+    }
+}
+
 impl Display for Tile {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -117,7 +320,10 @@ impl Display for Tile {
                 Tile::Air => ' '.to_string(),
                 Tile::Ramp(x) => x.to_string(),
                 Tile::Player => '@'.to_string(),
-                Tile::Key => '$'.to_string(),
+                Tile::Key(_) => '$'.to_string(),
+                Tile::Door(_) => '▯'.to_string(),
+                Tile::Hazard => '▲'.to_string(),
+                Tile::Checkpoint => '⚑'.to_string(),
                 Tile::Goal => '0'.to_string()
             }
         )
@@ -153,15 +359,193 @@ impl TileImageUnknownPixel {
     }
 }
 
+/// Every unknown pixel of an image, grouped by colour
+#[derive(Debug, Clone, Error)]
+pub struct TileImageUnknownPixels {
+    pub groups: Vec<UnknownPixelGroup>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownPixelGroup {
+    pub pixel: Rgba<u8>,
+    pub positions: Vec<(u32, u32)>,
+    /// The closest colour in the palette and the tile it maps to
+    pub suggestion: Option<(PaletteColor, Tile)>,
+}
+
+impl TileImageUnknownPixels {
+    pub fn new(pixels: Vec<TileImageUnknownPixel>, palette: &TilePalette) -> Self {
+        let mut groups: Vec<UnknownPixelGroup> = vec![];
+        for TileImageUnknownPixel { x, y, pixel } in pixels {
+            match groups.iter_mut().find(|g| g.pixel == pixel) {
+                Some(group) => group.positions.push((x, y)),
+                None => groups.push(UnknownPixelGroup {
+                    pixel,
+                    positions: vec![(x, y)],
+                    suggestion: palette.nearest(pixel),
+                }),
+            }
+        }
+        groups.sort_by_key(|g| std::cmp::Reverse(g.positions.len()));
+
+        Self { groups }
+    }
+}
+
+impl Display for TileImageUnknownPixels {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let count: usize = self.groups.iter().map(|g| g.positions.len()).sum();
+        write!(f, "{} unknown pixels in {} colours", count, self.groups.len())?;
+
+        for group in self.groups.iter() {
+            write!(
+                f,
+                "\n  {} at {}",
+                PaletteColor(group.pixel.0),
+                group.positions.iter().map(|(x, y)| format!("({x},{y})")).join(", ")
+            )?;
+            if let Some((color, tile)) = group.suggestion {
+                write!(f, " (closest: {color}, {tile:?})")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&DynamicImage> for Grid<(Tile, Surface)> {
+    type Error = TileImageUnknownPixels;
+
+    fn try_from(value: &DynamicImage) -> Result<Self, Self::Error> {
+        TilePalette::default().parse_image(value)
+    }
+}
+
 impl TryFrom<&DynamicImage> for Grid<Tile> {
-    type Error = TileImageUnknownPixel;
+    type Error = TileImageUnknownPixels;
 
     fn try_from(value: &DynamicImage) -> Result<Self, Self::Error> {
-        Grid::try_from_iter(
-            (0..value.height()).map(move |y| {
-                (0..value.width()).map(move |x| Tile::try_from(value.get_pixel(x, y)))
-            }),
-        )
-        .map_err(|(x, y, e)| TileImageUnknownPixel::new(x as u32, y as u32, e.0))
+        Grid::<(Tile, Surface)>::try_from(value).map(|grid| grid.unzip().0)
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Unknown character {char:?} at line {line}, column {column}")]
+pub struct TileTextUnknownChar {
+    pub line: usize,
+    pub column: usize,
+    pub char: char,
+}
+
+impl TileTextUnknownChar {
+    pub fn new(line: usize, column: usize, char: char) -> Self {
+        Self { line, column, char }
+    }
+}
+
+/// Parses the plain-text level format, one row per line.
+/// Short lines are padded with [`Tile::Air`] so trailing whitespace doesn't matter.
+impl TryFrom<&str> for Grid<(Tile, Surface)> {
+    type Error = TileTextUnknownChar;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let width = value.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        Grid::try_from_iter(value.lines().map(|line| {
+            line.chars()
+                .map(parse_tile_char)
+                .chain(std::iter::repeat(Ok((Tile::Air, Surface::Normal))))
+                .take(width)
+        }))
+        // lines and columns are 1-based, like in a text editor
+        .map_err(|(x, y, c)| TileTextUnknownChar::new(y + 1, x + 1, c))
+    }
+}
+
+impl TryFrom<&str> for Grid<Tile> {
+    type Error = TileTextUnknownChar;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Grid::<(Tile, Surface)>::try_from(value).map(|grid| grid.unzip().0)
+    }
+}
+
+impl Grid<(Tile, Surface)> {
+    /// Writes the grid in the plain-text level format, parseable again with `Grid::try_from(&str)`
+    pub fn to_text(&self) -> String {
+        (0..self.h)
+            .map(|y| {
+                (0..self.w)
+                    .map(|x| {
+                        let &(tile, surface) = self.get(x, y).unwrap();
+                        tile_char(tile, surface)
+                    })
+                    .chain(std::iter::once('\n'))
+                    .collect::<String>()
+            })
+            .collect()
+    }
+}
+
+impl Grid<Tile> {
+    /// The tiles of the door at (x, y): the door tiles of the same colour connected to it.
+    /// Empty if (x, y) isn't a door.
+    pub fn door_at(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        match self.get(x, y) {
+            Some(&door @ Tile::Door(_)) => self.flood_fill(x, y, Connectivity::Four, |&tile| tile == door),
+            _ => vec![],
+        }
+    }
+
+    /// The first tile of every door, in reading order, see [`Grid::door_at`]
+    pub fn doors(&self) -> Vec<(usize, usize)> {
+        let mut seen = Grid::filled(self.w, self.h, false);
+        let mut doors = vec![];
+
+        for ((x, y), &tile) in self.iter() {
+            if !matches!(tile, Tile::Door(_)) {
+                continue;
+            }
+            if !self.flood_fill_unseen(x, y, self.rect(), Connectivity::Four, |&t| t == tile, &mut seen).is_empty() {
+                doors.push((x, y));
+            }
+        }
+        doors
+    }
+
+    /// Writes the grid in the plain-text level format, parseable again with `Grid::try_from(&str)`
+    pub fn to_text(&self) -> String {
+        self.clone().map(|t| (t, Surface::Normal)).to_text()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::palette::every_entry;
+
+    #[test]
+    fn text_round_trips_every_entry() {
+        let grid = every_entry();
+        assert_eq!(Grid::<(Tile, Surface)>::try_from(grid.to_text().as_str()).unwrap(), grid);
+    }
+
+    #[test]
+    fn tiles_round_trip_through_text() {
+        let grid = every_entry().unzip().0;
+        assert_eq!(Grid::<Tile>::try_from(grid.to_text().as_str()).unwrap(), grid);
+    }
+
+    #[test]
+    fn tile_char_inverts_parse_tile_char() {
+        for (_, tile, surface) in DEFAULT_PALETTE {
+            assert_eq!(parse_tile_char(tile_char(tile, surface)), Ok((tile, surface)));
+        }
+    }
+
+    #[test]
+    fn short_lines_are_padded_with_air() {
+        let grid = Grid::<Tile>::try_from("X\nX@").unwrap();
+        assert_eq!(grid, Grid::new(vec![vec![Tile::Solid, Tile::Air], vec![Tile::Solid, Tile::Player]]));
     }
 }
@@ -0,0 +1,683 @@
+use crate::tileset::collider::ColliderMode;
+use crate::tileset::grid::Grid;
+use crate::tileset::load::{LevelFiles, TileAnchor, TileGeometry, TileGridAsset, TileGridAssetLoaderError};
+use crate::tileset::tile::{Surface, Tile, UnknownName};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+const FLIPPED_X: u32 = 0x8000_0000;
+const FLIPPED_Y: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const GID_MASK: u32 = 0x0FFF_FFFF;
+
+/// A map made with [Tiled](https://www.mapeditor.org/), reduced to what a [`TileGridAsset`] needs.
+///
+/// Tileset tiles are mapped to [`Tile`]s by a `tile` custom property (e.g. `solid`, `ramp_sw`)
+/// or by their class, and can set their [`Surface`] with a `surface` property.
+/// Objects in object layers are placed as the tile named by their class (e.g. `player`, `goal`).
+#[derive(Debug, Clone)]
+pub struct TiledMap {
+    pub width: usize,
+    pub height: usize,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<TiledTileset>,
+    pub layers: Vec<TiledLayer>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TiledTileset {
+    pub first_gid: u32,
+    /// The path of an external tileset, relative to the map
+    pub source: Option<String>,
+    pub tiles: HashMap<u32, TiledTile>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TiledTile {
+    pub tile: Option<String>,
+    pub surface: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TiledLayer {
+    Tiles(Vec<u32>),
+    Objects(Vec<TiledObject>),
+}
+
+#[derive(Debug, Clone)]
+pub struct TiledObject {
+    pub class: String,
+    /// The centre of the object, in pixels from the top-left of the map
+    pub position: Vec2,
+}
+
+#[derive(Debug, Error)]
+pub enum TiledError {
+    #[error("Could not parse JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Could not parse XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Missing or invalid attribute {0:?}")]
+    Attribute(&'static str),
+    #[error("Unsupported layer encoding {0:?}, only CSV is supported")]
+    Encoding(String),
+    #[error("Infinite maps are not supported")]
+    Infinite,
+    #[error("Tiles are {width}x{height} pixels, only square tiles are supported")]
+    TileSize { width: u32, height: u32 },
+    #[error("Tile layer has {0} tiles, expected {1}")]
+    LayerSize(usize, usize),
+    #[error("Tile {0} has no \"tile\" property or class")]
+    Unmapped(u32),
+    #[error("Tile {gid}: {source}")]
+    Name { gid: u32, source: UnknownName },
+}
+
+impl TiledMap {
+    /// Parses a `.tmj` map
+    pub fn from_json(bytes: &[u8]) -> Result<Self, TiledError> {
+        let map: json::Map = serde_json::from_slice(bytes)?;
+        if map.infinite {
+            return Err(TiledError::Infinite);
+        }
+
+        let mut layers = vec![];
+        json::flatten_layers(map.layers, &mut layers)?;
+
+        Ok(Self {
+            width: map.width,
+            height: map.height,
+            tile_width: map.tilewidth,
+            tile_height: map.tileheight,
+            tilesets: map.tilesets.into_iter().map(TiledTileset::from).collect(),
+            layers,
+        })
+    }
+
+    /// Parses a `.tmx` map
+    pub fn from_xml(text: &str) -> Result<Self, TiledError> {
+        let doc = roxmltree::Document::parse(text)?;
+        let map = doc.root_element();
+        if xml::attr_or(map, "infinite", 0u8)? != 0 {
+            return Err(TiledError::Infinite);
+        }
+
+        let mut layers = vec![];
+        xml::collect_layers(map, &mut layers)?;
+
+        Ok(Self {
+            width: xml::attr(map, "width")?,
+            height: xml::attr(map, "height")?,
+            tile_width: xml::attr(map, "tilewidth")?,
+            tile_height: xml::attr(map, "tileheight")?,
+            tilesets: map
+                .children()
+                .filter(|n| n.has_tag_name("tileset"))
+                .map(|n| {
+                    Ok(TiledTileset {
+                        first_gid: xml::attr(n, "firstgid")?,
+                        source: n.attribute("source").map(str::to_string),
+                        tiles: xml::tiles(n),
+                    })
+                })
+                .collect::<Result<_, TiledError>>()?,
+            layers,
+        })
+    }
+
+    /// The width and height of the tiles, which have to be square
+    pub fn tile_size(&self) -> Result<f32, TiledError> {
+        if self.tile_width != self.tile_height {
+            return Err(TiledError::TileSize {
+                width: self.tile_width,
+                height: self.tile_height,
+            });
+        }
+        Ok(self.tile_width as f32)
+    }
+
+    /// Looks up the tile for a global tile id, applying its flip flags to ramps
+    pub fn tile(&self, gid: u32) -> Result<(Tile, Surface), TiledError> {
+        let id = gid & GID_MASK;
+        let tileset = self
+            .tilesets
+            .iter()
+            .filter(|t| t.first_gid <= id)
+            .max_by_key(|t| t.first_gid)
+            .ok_or(TiledError::Unmapped(id))?;
+        let properties = tileset
+            .tiles
+            .get(&(id - tileset.first_gid))
+            .ok_or(TiledError::Unmapped(id))?;
+
+        let name = properties.tile.as_deref().ok_or(TiledError::Unmapped(id))?;
+        let mut tile = Tile::from_str(name).map_err(|source| TiledError::Name { gid: id, source })?;
+        let surface = match properties.surface.as_deref() {
+            Some(surface) => Surface::from_str(surface)
+                .map_err(|source| TiledError::Name { gid: id, source })?,
+            None => Surface::Normal,
+        };
+
+        if let Tile::Ramp(orientation) = &mut tile {
+            // Tiled applies the diagonal flip first
+            if gid & FLIPPED_DIAGONALLY != 0 {
+                *orientation = orientation.transpose();
+            }
+            if gid & FLIPPED_X != 0 {
+                *orientation = orientation.flip_x();
+            }
+            if gid & FLIPPED_Y != 0 {
+                *orientation = orientation.flip_y();
+            }
+        }
+
+        Ok((tile, surface))
+    }
+
+    /// Combines all layers into one grid, later layers overwriting earlier ones
+    pub fn to_grid(&self) -> Result<Grid<(Tile, Surface)>, TiledError> {
+        let mut grid = vec![vec![(Tile::Air, Surface::Normal); self.width]; self.height];
+
+        for layer in self.layers.iter() {
+            match layer {
+                TiledLayer::Tiles(data) => {
+                    if data.len() != self.width * self.height {
+                        return Err(TiledError::LayerSize(data.len(), self.width * self.height));
+                    }
+
+                    for (i, &gid) in data.iter().enumerate() {
+                        if gid & GID_MASK != 0 {
+                            grid[i / self.width][i % self.width] = self.tile(gid)?;
+                        }
+                    }
+                }
+                TiledLayer::Objects(objects) => {
+                    for object in objects.iter() {
+                        let Ok(tile) = Tile::from_str(&object.class) else {
+                            warn!("ignoring Tiled object of unknown class {:?}", object.class);
+                            continue;
+                        };
+
+                        let x = (object.position.x / self.tile_width as f32).floor();
+                        let y = (object.position.y / self.tile_height as f32).floor();
+                        if x < 0.0 || y < 0.0 || x as usize >= self.width || y as usize >= self.height {
+                            warn!("ignoring Tiled object {:?} outside of the map", object.class);
+                            continue;
+                        }
+
+                        grid[y as usize][x as usize] = (tile, Surface::Normal);
+                    }
+                }
+            }
+        }
+
+        Ok(Grid::new(grid))
+    }
+}
+
+impl TiledTileset {
+    /// Parses the tiles of an external `.tsj` tileset
+    pub fn tiles_from_json(bytes: &[u8]) -> Result<HashMap<u32, TiledTile>, TiledError> {
+        let tileset: json::Tileset = serde_json::from_slice(bytes)?;
+        Ok(TiledTileset::from(tileset).tiles)
+    }
+
+    /// Parses the tiles of an external `.tsx` tileset
+    pub fn tiles_from_xml(text: &str) -> Result<HashMap<u32, TiledTile>, TiledError> {
+        let doc = roxmltree::Document::parse(text)?;
+        Ok(xml::tiles(doc.root_element()))
+    }
+}
+
+fn parse_csv(csv: &str) -> Result<Vec<u32>, TiledError> {
+    csv.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(|_| TiledError::Attribute("data")))
+        .collect()
+}
+
+mod json {
+    use super::*;
+    use serde_json::Value;
+
+    #[derive(Deserialize)]
+    pub struct Map {
+        pub width: usize,
+        pub height: usize,
+        pub tilewidth: u32,
+        pub tileheight: u32,
+        #[serde(default)]
+        pub infinite: bool,
+        pub layers: Vec<Layer>,
+        pub tilesets: Vec<Tileset>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    pub enum Layer {
+        #[serde(rename = "tilelayer")]
+        Tiles {
+            data: Data,
+            encoding: Option<String>,
+        },
+        ObjectGroup {
+            objects: Vec<Object>,
+        },
+        Group {
+            layers: Vec<Layer>,
+        },
+        #[serde(other)]
+        Other,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub enum Data {
+        Ids(Vec<u32>),
+        Encoded(String),
+    }
+
+    #[derive(Deserialize)]
+    pub struct Object {
+        #[serde(default, rename = "type")]
+        kind: String,
+        #[serde(default)]
+        class: String,
+        x: f32,
+        y: f32,
+        #[serde(default)]
+        width: f32,
+        #[serde(default)]
+        height: f32,
+        gid: Option<u32>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Tileset {
+        #[serde(default)]
+        firstgid: u32,
+        source: Option<String>,
+        #[serde(default)]
+        tiles: Vec<TilesetTile>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct TilesetTile {
+        id: u32,
+        #[serde(default, rename = "type")]
+        kind: String,
+        #[serde(default)]
+        class: String,
+        #[serde(default)]
+        properties: Vec<Property>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Property {
+        name: String,
+        value: Value,
+    }
+
+    impl From<Tileset> for TiledTileset {
+        fn from(value: Tileset) -> Self {
+            let tiles = value
+                .tiles
+                .into_iter()
+                .map(|tile| {
+                    let property = |name: &str| {
+                        tile.properties.iter().find(|p| p.name == name).map(|p| match &p.value {
+                            Value::String(s) => s.clone(),
+                            v => v.to_string(),
+                        })
+                    };
+                    let class = [&tile.class, &tile.kind]
+                        .into_iter()
+                        .find(|c| !c.is_empty())
+                        .cloned();
+
+                    let properties = TiledTile {
+                        tile: property("tile").or(class),
+                        surface: property("surface"),
+                    };
+                    (tile.id, properties)
+                })
+                .collect();
+
+            TiledTileset {
+                first_gid: value.firstgid,
+                source: value.source,
+                tiles,
+            }
+        }
+    }
+
+    pub fn flatten_layers(layers: Vec<Layer>, out: &mut Vec<TiledLayer>) -> Result<(), TiledError> {
+        for layer in layers {
+            match layer {
+                Layer::Tiles { data, encoding } => {
+                    let ids = match (data, encoding.as_deref()) {
+                        (Data::Ids(ids), _) => ids,
+                        (Data::Encoded(csv), Some("csv")) => parse_csv(&csv)?,
+                        (Data::Encoded(_), encoding) => {
+                            return Err(TiledError::Encoding(encoding.unwrap_or_default().to_string()))
+                        }
+                    };
+                    out.push(TiledLayer::Tiles(ids));
+                }
+                Layer::ObjectGroup { objects } => {
+                    let objects = objects
+                        .into_iter()
+                        .map(|o| {
+                            // tile objects are anchored at their bottom-left, everything else at the top-left
+                            let y = match o.gid {
+                                Some(_) => o.y - o.height / 2.0,
+                                None => o.y + o.height / 2.0,
+                            };
+                            TiledObject {
+                                class: if o.class.is_empty() { o.kind } else { o.class },
+                                position: Vec2::new(o.x + o.width / 2.0, y),
+                            }
+                        })
+                        .collect();
+                    out.push(TiledLayer::Objects(objects));
+                }
+                Layer::Group { layers } => flatten_layers(layers, out)?,
+                Layer::Other => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+mod xml {
+    use super::*;
+    use roxmltree::Node;
+
+    pub fn attr<T: FromStr>(node: Node, name: &'static str) -> Result<T, TiledError> {
+        node.attribute(name)
+            .and_then(|a| a.parse().ok())
+            .ok_or(TiledError::Attribute(name))
+    }
+
+    pub fn attr_or<T: FromStr>(node: Node, name: &'static str, default: T) -> Result<T, TiledError> {
+        match node.attribute(name) {
+            Some(a) => a.parse().map_err(|_| TiledError::Attribute(name)),
+            None => Ok(default),
+        }
+    }
+
+    fn class(node: Node) -> Option<String> {
+        node.attribute("class")
+            .or(node.attribute("type"))
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+    }
+
+    fn property(node: Node, name: &str) -> Option<String> {
+        node.children()
+            .filter(|n| n.has_tag_name("properties"))
+            .flat_map(|n| n.children())
+            .find(|n| n.has_tag_name("property") && n.attribute("name") == Some(name))
+            .and_then(|n| n.attribute("value").or(n.text()))
+            .map(str::to_string)
+    }
+
+    pub fn tiles(tileset: Node) -> HashMap<u32, TiledTile> {
+        tileset
+            .children()
+            .filter(|n| n.has_tag_name("tile"))
+            .filter_map(|n| {
+                let properties = TiledTile {
+                    tile: property(n, "tile").or(class(n)),
+                    surface: property(n, "surface"),
+                };
+                Some((attr(n, "id").ok()?, properties))
+            })
+            .collect()
+    }
+
+    pub fn collect_layers(parent: Node, out: &mut Vec<TiledLayer>) -> Result<(), TiledError> {
+        for node in parent.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "layer" => {
+                    let data = node
+                        .children()
+                        .find(|n| n.has_tag_name("data"))
+                        .ok_or(TiledError::Attribute("data"))?;
+                    match data.attribute("encoding") {
+                        Some("csv") => out.push(TiledLayer::Tiles(parse_csv(data.text().unwrap_or_default())?)),
+                        None => out.push(TiledLayer::Tiles(
+                            data.children()
+                                .filter(|n| n.has_tag_name("tile"))
+                                .map(|n| attr_or(n, "gid", 0))
+                                .collect::<Result<_, _>>()?,
+                        )),
+                        Some(encoding) => return Err(TiledError::Encoding(encoding.to_string())),
+                    }
+                }
+                "objectgroup" => {
+                    let objects = node
+                        .children()
+                        .filter(|n| n.has_tag_name("object"))
+                        .map(|n| {
+                            let (x, y): (f32, f32) = (attr(n, "x")?, attr(n, "y")?);
+                            let (w, h): (f32, f32) = (attr_or(n, "width", 0.0)?, attr_or(n, "height", 0.0)?);
+                            // tile objects are anchored at their bottom-left, everything else at the top-left
+                            let y = match n.attribute("gid") {
+                                Some(_) => y - h / 2.0,
+                                None => y + h / 2.0,
+                            };
+
+                            Ok(TiledObject {
+                                class: class(n).unwrap_or_default(),
+                                position: Vec2::new(x + w / 2.0, y),
+                            })
+                        })
+                        .collect::<Result<_, TiledError>>()?;
+                    out.push(TiledLayer::Objects(objects));
+                }
+                "group" => collect_layers(node, out)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct TiledAssetLoader;
+
+/// Settings for [`TiledAssetLoader`], the tile size is taken from the map
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TiledLoaderSettings {
+    pub anchor: TileAnchor,
+    pub collider_mode: ColliderMode,
+}
+
+/// Reads a Tiled map and the external tilesets it references
+pub(crate) async fn load_tiled_map(
+    files: &mut impl LevelFiles,
+    path: &Path,
+    bytes: &[u8],
+    settings: &TiledLoaderSettings,
+) -> Result<TileGridAsset, TileGridAssetLoaderError> {
+    let mut map = match path.extension().and_then(|e| e.to_str()) {
+        Some("tmx") => TiledMap::from_xml(std::str::from_utf8(bytes)?)?,
+        _ => TiledMap::from_json(bytes)?,
+    };
+
+    let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    for tileset in map.tilesets.iter_mut() {
+        let Some(source) = tileset.source.clone() else {
+            continue;
+        };
+
+        let bytes = files.read(&dir.join(&source)).await?;
+        tileset.tiles = if source.ends_with(".tsx") {
+            TiledTileset::tiles_from_xml(std::str::from_utf8(&bytes)?)?
+        } else {
+            TiledTileset::tiles_from_json(&bytes)?
+        };
+    }
+
+    let tile_size = map.tile_size()?;
+    let (grid, surfaces) = map.to_grid()?.unzip();
+    let geometry = TileGeometry {
+        tile_size,
+        anchor: settings.anchor,
+    };
+
+    Ok(TileGridAsset::new(grid, surfaces, geometry, settings.collider_mode)?)
+}
+
+impl AssetLoader for TiledAssetLoader {
+    type Asset = TileGridAsset;
+    type Settings = TiledLoaderSettings;
+    type Error = TileGridAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let path = load_context.path().to_path_buf();
+        load_tiled_map(load_context, &path, &bytes, settings).await
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj", "tmx"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// Tiles 0 to 2 are a solid, a ramp and an icy solid, tile 3 has an unknown class
+    const TSJ: &str = r#"{
+        "tiles": [
+            { "id": 0, "type": "solid" },
+            { "id": 1, "properties": [{ "name": "tile", "type": "string", "value": "ramp_sw" }] },
+            { "id": 2, "class": "solid", "properties": [{ "name": "surface", "type": "string", "value": "ice" }] },
+            { "id": 3, "class": "lava" }
+        ]
+    }"#;
+
+    const TSX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <tileset name="tiles" tilewidth="16" tileheight="16" tilecount="4" columns="4">
+            <tile id="0" type="solid"/>
+            <tile id="1"><properties><property name="tile" value="ramp_sw"/></properties></tile>
+            <tile id="2" class="solid"><properties><property name="surface" value="ice"/></properties></tile>
+            <tile id="3" class="lava"/>
+        </tileset>"#;
+
+    /// The ramp unflipped, flipped horizontally, vertically and diagonally, then a solid and an icy solid.
+    /// The player is a rectangle object anchored at its top-left, the goal a tile object anchored at its bottom-left.
+    const TMJ: &str = r#"{
+        "width": 4, "height": 2, "tilewidth": 16, "tileheight": 16, "infinite": false,
+        "tilesets": [{ "firstgid": 1, "source": "tiles.tsj" }],
+        "layers": [
+            { "type": "tilelayer", "data": [2, 2147483650, 1073741826, 536870914, 1, 3, 0, 0] },
+            { "type": "group", "layers": [{ "type": "objectgroup", "objects": [
+                { "class": "player", "x": 32, "y": 16, "width": 16, "height": 16 },
+                { "type": "goal", "gid": 1, "x": 48, "y": 32, "width": 16, "height": 16 }
+            ] }] }
+        ]
+    }"#;
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <map width="4" height="2" tilewidth="16" tileheight="16" infinite="0">
+            <tileset firstgid="1" source="tiles.tsx"/>
+            <layer name="tiles" width="4" height="2">
+                <data encoding="csv">2,2147483650,1073741826,536870914,
+1,3,0,0</data>
+            </layer>
+            <objectgroup name="objects">
+                <object class="player" x="32" y="16" width="16" height="16"/>
+                <object type="goal" gid="1" x="48" y="32" width="16" height="16"/>
+            </objectgroup>
+        </map>"#;
+
+    /// The level both maps describe
+    const TEXT: &str = "\\/,`\nXI@0";
+
+    /// The tilesets next to the maps
+    struct Tilesets;
+
+    impl LevelFiles for Tilesets {
+        async fn read(&mut self, path: &Path) -> Result<Vec<u8>, TileGridAssetLoaderError> {
+            match self.read_optional(path).await? {
+                Some(bytes) => Ok(bytes),
+                None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+            }
+        }
+
+        async fn read_optional(&mut self, path: &Path) -> Result<Option<Vec<u8>>, TileGridAssetLoaderError> {
+            Ok([("levels/tiles.tsj", TSJ), ("levels/tiles.tsx", TSX)]
+                .into_iter()
+                .find(|&(p, _)| Path::new(p) == path)
+                .map(|(_, text)| text.as_bytes().to_vec()))
+        }
+    }
+
+    fn load(path: &str, map: &str) -> Result<TileGridAsset, TileGridAssetLoaderError> {
+        bevy::tasks::block_on(load_tiled_map(&mut Tilesets, Path::new(path), map.as_bytes(), &default()))
+    }
+
+    fn assert_loads_text_level(path: &str, map: &str) {
+        let level = load(path, map).unwrap();
+        let (grid, surfaces) = Grid::<(Tile, Surface)>::try_from(TEXT).unwrap().unzip();
+        assert_eq!(level.grid, grid);
+        assert_eq!(level.surfaces, surfaces);
+        assert_eq!(level.geometry.tile_size, 16.0);
+    }
+
+    #[test]
+    fn loads_json_maps() {
+        assert_loads_text_level("levels/map.tmj", TMJ);
+    }
+
+    #[test]
+    fn loads_xml_maps() {
+        assert_loads_text_level("levels/map.tmx", TMX);
+    }
+
+    #[test]
+    fn unknown_tiles_are_errors() {
+        let unknown_class = TMJ.replacen("[2,", "[4,", 1);
+        assert!(matches!(
+            load("levels/map.tmj", &unknown_class),
+            Err(TileGridAssetLoaderError::Tiled(TiledError::Name { gid: 4, .. }))
+        ));
+
+        let missing_tile = TMX.replacen(">2,", ">9,", 1);
+        assert!(matches!(
+            load("levels/map.tmx", &missing_tile),
+            Err(TileGridAssetLoaderError::Tiled(TiledError::Unmapped(9)))
+        ));
+    }
+
+    #[test]
+    fn tiles_have_to_be_square() {
+        let map = TMJ.replace(r#""tileheight": 16"#, r#""tileheight": 8"#);
+        assert!(matches!(
+            load("levels/map.tmj", &map),
+            Err(TileGridAssetLoaderError::Tiled(TiledError::TileSize { width: 16, height: 8 }))
+        ));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::player::PLAYER_RADIUS;
+use crate::tileset::grid::Connectivity;
+use crate::tileset::load::TileGridAsset;
+use crate::tileset::tile::{KeyColor, Tile};
+use bevy::prelude::Vec2;
+use thiserror::Error;
+
+/// Something wrong with a level that still loads fine, found by [`validate`]
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum LevelProblem {
+    #[error("No player spawn")]
+    NoSpawn,
+    #[error("Player spawns at {others:?} are ignored, only the first one at {first:?} is used")]
+    ExtraSpawns {
+        first: (usize, usize),
+        others: Vec<(usize, usize)>,
+    },
+    #[error("Player spawn at {0:?} is walled in by solid tiles")]
+    EnclosedSpawn((usize, usize)),
+    #[error("The player spawning at {spawn:?} would be stuck in the tile at {tile:?}")]
+    EmbeddedSpawn {
+        spawn: (usize, usize),
+        tile: (usize, usize),
+    },
+    #[error("No goal")]
+    NoGoal,
+    #[error("Key at {0:?} doesn't open anything")]
+    UnusedKey((usize, usize)),
+    #[error("No key opens the door at {0:?}")]
+    LockedDoor((usize, usize)),
+    #[error("{doors} doors but only {keys} keys, and each door uses up a key")]
+    TooFewKeys { doors: usize, keys: usize },
+}
+
+/// How much a [`LevelProblem`] matters
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Severity {
+    /// The level plays, but likely not as intended, like a level that is still being built
+    Warning,
+    /// The player can't play the level, or gets stuck in it
+    Error,
+}
+
+impl LevelProblem {
+    pub fn severity(&self) -> Severity {
+        match self {
+            // test levels have no goal, and extra spawns or keys are simply unused
+            LevelProblem::NoGoal | LevelProblem::ExtraSpawns { .. } | LevelProblem::UnusedKey(_) => {
+                Severity::Warning
+            }
+            LevelProblem::NoSpawn
+            | LevelProblem::EnclosedSpawn(_)
+            | LevelProblem::EmbeddedSpawn { .. }
+            | LevelProblem::LockedDoor(_)
+            | LevelProblem::TooFewKeys { .. } => Severity::Error,
+        }
+    }
+}
+
+fn is_solid(tile: &Tile) -> bool {
+    matches!(tile, Tile::Solid | Tile::Ramp(_) | Tile::Door(_))
+}
+
+/// The corners of the collider of a solid tile, relative to its centre in tiles
+fn solid_corners(tile: Tile) -> Option<Vec<Vec2>> {
+    match tile {
+        Tile::Solid | Tile::Door(_) => Some(vec![
+            Vec2::new(-0.5, -0.5),
+            Vec2::new(-0.5, 0.5),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(0.5, -0.5),
+        ]),
+        Tile::Ramp(orientation) => Some(orientation.to_triangle().to_vec()),
+        _ => None,
+    }
+}
+
+/// Whether a circle overlaps the inside of a convex polygon, touching doesn't count
+fn circle_overlaps(center: Vec2, radius: f32, corners: &[Vec2]) -> bool {
+    let edges = || corners.iter().zip(corners.iter().cycle().skip(1));
+
+    let sides = edges().map(|(&a, &b)| (b - a).perp_dot(center - a)).collect::<Vec<_>>();
+    let inside = sides.iter().all(|&side| side >= 0.0) || sides.iter().all(|&side| side <= 0.0);
+
+    inside
+        || edges().any(|(&a, &b)| {
+            let t = ((center - a).dot(b - a) / (b - a).length_squared()).clamp(0.0, 1.0);
+            center.distance(a + (b - a) * t) < radius
+        })
+}
+
+/// The first solid tile the player's collider overlaps when spawning at (x, y)
+fn embedding_tile(level: &TileGridAsset, x: usize, y: usize) -> Option<(usize, usize)> {
+    let geometry = level.geometry;
+    let center = geometry.tile_center(x, y);
+    let reach = (PLAYER_RADIUS / geometry.tile_size).ceil() as i64;
+
+    (-reach..=reach)
+        .flat_map(|dy| (-reach..=reach).map(move |dx| (x as i64 + dx, y as i64 + dy)))
+        .find_map(|(tx, ty)| {
+            let corners = solid_corners(*level.grid.get_i(tx, ty)?)?;
+            let (tx, ty) = (tx as usize, ty as usize);
+            let tile_center = geometry.tile_center(tx, ty);
+            let corners = corners
+                .into_iter()
+                .map(|corner| tile_center + corner * geometry.tile_size)
+                .collect::<Vec<_>>();
+
+            circle_overlaps(center, PLAYER_RADIUS, &corners).then_some((tx, ty))
+        })
+}
+
+/// Checks the things the game relies on but the loaders don't, in reading order of the tiles
+pub fn validate(level: &TileGridAsset) -> Vec<LevelProblem> {
+    let grid = &level.grid;
+    let mut problems = vec![];
+
+    let spawns = grid.positions(|tile| *tile == Tile::Player);
+    match spawns.as_slice() {
+        [] => problems.push(LevelProblem::NoSpawn),
+        [first, others @ ..] => {
+            if !others.is_empty() {
+                problems.push(LevelProblem::ExtraSpawns {
+                    first: *first,
+                    others: others.to_vec(),
+                });
+            }
+
+            // the player can't get anywhere if there is no free tile next to the spawn
+            let (x, y) = *first;
+            if grid.flood_fill(x, y, Connectivity::Four, |tile| !is_solid(tile)).len() == 1 {
+                problems.push(LevelProblem::EnclosedSpawn(*first));
+            }
+            if let Some(tile) = embedding_tile(level, x, y) {
+                problems.push(LevelProblem::EmbeddedSpawn { spawn: *first, tile });
+            }
+        }
+    }
+
+    if grid.positions(|tile| *tile == Tile::Goal).is_empty() {
+        problems.push(LevelProblem::NoGoal);
+    }
+
+    let keys: Vec<((usize, usize), KeyColor)> = grid
+        .iter()
+        .filter_map(|(pos, tile)| match tile {
+            Tile::Key(color) => Some((pos, *color)),
+            _ => None,
+        })
+        .collect();
+    let doors: Vec<((usize, usize), KeyColor)> = grid
+        .doors()
+        .into_iter()
+        .filter_map(|(x, y)| match grid.get(x, y) {
+            Some(Tile::Door(color)) => Some(((x, y), *color)),
+            _ => None,
+        })
+        .collect();
+
+    for &(key, key_color) in &keys {
+        if !doors.iter().any(|&(_, door_color)| key_color.opens(door_color)) {
+            problems.push(LevelProblem::UnusedKey(key));
+        }
+    }
+    for &(door, door_color) in &doors {
+        if !keys.iter().any(|&(_, key_color)| key_color.opens(door_color)) {
+            problems.push(LevelProblem::LockedDoor(door));
+        }
+    }
+    if doors.len() > keys.len() {
+        problems.push(LevelProblem::TooFewKeys {
+            doors: doors.len(),
+            keys: keys.len(),
+        });
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::collider::ColliderMode;
+    use crate::tileset::grid::Grid;
+    use crate::tileset::load::TileGeometry;
+    use crate::tileset::tile::Surface;
+
+    fn level(text: &str, tile_size: f32) -> TileGridAsset {
+        let (grid, surfaces) = Grid::<(Tile, Surface)>::try_from(text).unwrap().unzip();
+        let geometry = TileGeometry {
+            tile_size,
+            ..Default::default()
+        };
+        TileGridAsset::new(grid, surfaces, geometry, ColliderMode::default()).unwrap()
+    }
+
+    fn spawn_problems(text: &str, tile_size: f32) -> Vec<LevelProblem> {
+        validate(&level(text, tile_size))
+            .into_iter()
+            .filter(|p| matches!(p, LevelProblem::EnclosedSpawn(_) | LevelProblem::EmbeddedSpawn { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn free_spawn_is_fine() {
+        assert_eq!(spawn_problems("XXXXX\nX   X\nX @ X\nXXXXX", 16.0), vec![]);
+    }
+
+    #[test]
+    fn doors_wall_in_the_spawn() {
+        assert_eq!(
+            spawn_problems(" X \nD@X\n X ", 16.0),
+            vec![LevelProblem::EnclosedSpawn((1, 1))]
+        );
+    }
+
+    #[test]
+    fn small_tiles_embed_the_spawn() {
+        // the player is wider than a tile, so it overlaps the wall next to it
+        assert_eq!(
+            spawn_problems("    \nX@  \n    ", 8.0),
+            vec![LevelProblem::EmbeddedSpawn {
+                spawn: (1, 1),
+                tile: (0, 1),
+            }]
+        );
+    }
+
+    #[test]
+    fn ramp_slopes_embed_the_spawn() {
+        // the slope of the ramp below reaches up to the spawn tile's centre
+        assert_eq!(
+            spawn_problems("   \n @ \n / \n", 8.0),
+            vec![LevelProblem::EmbeddedSpawn {
+                spawn: (1, 1),
+                tile: (1, 2),
+            }]
+        );
+        // but one tile further down leaves enough room
+        assert_eq!(spawn_problems("   \n @ \n   \n / \n", 8.0), vec![]);
+    }
+
+    #[test]
+    fn keys_match_doors_by_colour() {
+        let problems = validate(&level("@0rB", 16.0));
+        assert_eq!(
+            problems,
+            vec![LevelProblem::UnusedKey((2, 0)), LevelProblem::LockedDoor((3, 0))]
+        );
+    }
+
+    #[test]
+    fn missing_goal_and_unused_keys_only_warn() {
+        let problems = validate(&level("@ $", 16.0));
+        assert_eq!(problems, vec![LevelProblem::NoGoal, LevelProblem::UnusedKey((2, 0))]);
+        assert!(problems.iter().all(|p| p.severity() == Severity::Warning));
+        assert_eq!(validate(&level("@0B", 16.0))[0].severity(), Severity::Error);
+    }
+}
@@ -3,8 +3,11 @@ use bevy::color;
 use bevy::color::palettes::css::RED;
 use bevy::prelude::*;
 use bevy::prelude::TransformSystem::TransformPropagate;
+use crate::death::Dying;
 use crate::mouse::MouseCoords;
+use crate::tileset::load::NoGrapple;
 use crate::state::GamePhase::InGame;
+use crate::state::GamePhase;
 use crate::state::Paused;
 
 pub struct WebPlugin;
@@ -22,7 +25,7 @@ impl Plugin for WebPlugin {
             .add_systems(
                 Update,
                 ((
-                     handle_input,
+                     handle_input.run_if(not(in_state(GamePhase::Completed))),
                      (
                          move_and_attach_web,  // Moving
                          keep_web_attached,  // Attached or Idle
@@ -54,6 +57,16 @@ pub struct WebSource {
     pub joint: Option<Entity>,
 }
 
+impl WebSource {
+    /// Lets go of the web, despawning its joint right away
+    pub fn release(&mut self, state: &mut WebState, commands: &mut Commands) {
+        *state = WebState::Idle;
+        if let Some(joint) = self.joint.take().and_then(|e| commands.get_entity(e)) {
+            joint.despawn_recursive();
+        }
+    }
+}
+
 #[derive(Component, Default, Debug, Reflect, PartialEq, Copy, Clone)]
 pub enum WebState {
     #[default]
@@ -91,6 +104,17 @@ pub struct WebStats {
     pub max_travel_distance: f32,
 }
 
+impl Default for WebStats {
+    fn default() -> Self {
+        Self {
+            pull_force: 96000.0,
+            travel_speed: 640.0,
+            radius: 2.0,
+            max_travel_distance: 240.0,
+        }
+    }
+}
+
 #[derive(Event, Debug)]
 pub struct WebAttachEvent(pub Entity);
 
@@ -100,6 +124,7 @@ pub fn handle_input(
     key_input: Res<ButtonInput<KeyCode>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     mouse_pos: Res<MouseCoords>,
+    dying: Query<(), With<Dying>>,
 ) {
     let pull = key_input.pressed(KeyCode::Space);
     let swing = key_input.pressed(KeyCode::ShiftLeft);
@@ -107,6 +132,10 @@ pub fn handle_input(
     let just_throw = mouse_input.any_just_pressed([MouseButton::Left, MouseButton::Right]);
 
     for (mut web_state, mut transform, source) in query.iter_mut() {
+        if dying.contains(source.player) {
+            continue;
+        }
+
         match web_state.as_mut() {
             WebState::Idle => {
                 if just_throw {
@@ -147,6 +176,7 @@ fn move_and_attach_web(
     mut query: Query<(&WebSource, &WebStats, &mut WebState, &mut Transform)>,
     q_position: Query<&GlobalTransform>,
     spatial_query: SpatialQuery,
+    no_grapple: Query<(), With<NoGrapple>>,
     time: Res<Time>,
     mut attach_event: EventWriter<WebAttachEvent>,
 ) {
@@ -171,6 +201,11 @@ fn move_and_attach_web(
             false,
             SpatialQueryFilter::default().with_excluded_entities([source.player]),
         ) {
+            if no_grapple.contains(hit.entity) {
+                *state = WebState::Idle;
+                continue;
+            }
+
             let cur = q_position.get(hit.entity).unwrap().translation();
             let offset = hit.point1 - cur.truncate();
 
@@ -192,16 +227,21 @@ fn move_and_attach_web(
 }
 
 fn keep_web_attached(
-    mut webs: Query<(&mut Transform, &WebSource, &WebState)>,
+    mut commands: Commands,
+    mut webs: Query<(&mut Transform, &mut WebSource, &mut WebState)>,
     position: Query<&GlobalTransform>,
 ) {
-    for (mut transform, source, state) in webs.iter_mut() {
-        match state {
+    for (mut transform, mut source, mut state) in webs.iter_mut() {
+        match *state {
             WebState::Idle => {}
             WebState::Firing { .. } => {}
             WebState::Attached { target, offset, .. } => {
-                let target = position.get(*target).unwrap().translation();
-                transform.translation = target + offset.extend(0.0);
+                // what we were attached to is gone, e.g. an opened door or a rebuilt collider chunk
+                let Ok(target) = position.get(target) else {
+                    source.release(&mut state, &mut commands);
+                    continue;
+                };
+                transform.translation = target.translation() + offset.extend(0.0);
             }
         }
     }
@@ -280,8 +320,11 @@ fn handle_joint(
         // let mut joint = player.get_mut(source.joint).unwrap();
         match *state {
             WebState::Idle | WebState::Firing { .. } => {
-                if let Some(joint) = source.joint.and_then(|e| commands.get_entity(e)) {
-                    joint.despawn_recursive();
+                if let Some(joint) = source.joint {
+                    source.joint = None;
+                    if let Some(joint) = commands.get_entity(joint) {
+                        joint.despawn_recursive();
+                    }
                 }
             }
             WebState::Attached { target, pull, swing, offset } => {